@@ -0,0 +1,109 @@
+//! Resolution of texture and sound paths referenced by defs against the loose asset
+//! folders (`Textures/`, `Sounds/`) of every content root under `Data/` and of the scanned
+//! mods, for `--check-assets`.
+//!
+//! The official content ships its assets packed into the game's resource files, so content
+//! roots without the relevant folder can't be checked and are skipped rather than reported.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{mods, DefElement, RimWorldDef};
+
+/// Elements holding a path below `Textures/`.
+const TEXTURE_FIELDS: &[&str] = &["texPath", "uiIconPath", "iconPath", "texPathFemale", "texPathThumbnail"];
+const TEXTURE_EXTENSIONS: &[&str] = &["png", "dds", "jpg", "jpeg", "psd"];
+const SOUND_EXTENSIONS: &[&str] = &["wav", "ogg", "mp3"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssetKind {
+    Texture,
+    Sound,
+}
+
+impl AssetKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            AssetKind::Texture => "texture",
+            AssetKind::Sound => "sound",
+        }
+    }
+
+    fn folder(self) -> &'static str {
+        match self {
+            AssetKind::Texture => "Textures",
+            AssetKind::Sound => "Sounds",
+        }
+    }
+
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            AssetKind::Texture => TEXTURE_EXTENSIONS,
+            AssetKind::Sound => SOUND_EXTENSIONS,
+        }
+    }
+}
+
+/// Every asset path the def references, in document order.
+pub fn referenced_assets(def: &RimWorldDef) -> Vec<(AssetKind, String)> {
+    let mut assets = Vec::new();
+    let mut pending: Vec<&DefElement> = def.elements.iter().rev().collect();
+
+    while let Some(element) = pending.pop() {
+        pending.extend(element.children.iter().rev());
+
+        let kind = match element.name.as_str() {
+            name if TEXTURE_FIELDS.contains(&name) => AssetKind::Texture,
+            "clipPath" | "clipFolderPath" => AssetKind::Sound,
+            _ => continue,
+        };
+        if let Some(path) = element.content.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            assets.push((kind, path.to_string()));
+        }
+    }
+
+    assets
+}
+
+/// The asset folders of all content roots (`Data/Core`, `Data/Biotech`, ..., and those of
+/// each mod folder).
+pub struct AssetRoots {
+    roots: Vec<PathBuf>,
+}
+
+impl AssetRoots {
+    pub fn find(data_dir: &Path, mod_dirs: &[PathBuf]) -> Self {
+        let mut roots: Vec<PathBuf> = fs::read_dir(data_dir)
+            .map(|entries| entries.flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect())
+            .unwrap_or_default();
+        roots.extend(mod_dirs.iter().flat_map(|mod_dir| mods::content_roots(mod_dir)));
+        Self { roots }
+    }
+
+    /// Whether the asset exists in any content root, or None when no content root has
+    /// loose assets of that kind to check against.
+    pub fn exists(&self, kind: AssetKind, asset_path: &str) -> Option<bool> {
+        let folders: Vec<PathBuf> = self.roots.iter()
+            .map(|root| root.join(kind.folder()))
+            .filter(|folder| folder.is_dir())
+            .collect();
+        if folders.is_empty() {
+            return None;
+        }
+
+        // A path may name a single file, a folder of variants (random and appearance
+        // graphics, sound folders) or the base of directional `_north`/`_south` textures.
+        let found = folders.iter().any(|folder| {
+            let base = folder.join(asset_path);
+            base.is_dir() || ["", "_north", "_south", "_east"].iter().any(|suffix| {
+                kind.extensions().iter().any(|extension| {
+                    folder.join(format!("{}{}.{}", asset_path, suffix, extension)).is_file()
+                })
+            })
+        });
+        Some(found)
+    }
+}
@@ -0,0 +1,80 @@
+//! Parse cache for incremental runs (`--cache FILE`): the defs parsed from every file, keyed
+//! by path and SHA-256 of the content, stored as zstd-compressed JSON. Files whose content is
+//! unchanged reuse their cached defs; references are always rebuilt from the full set.
+//!
+//! Parsing also depends on the parser settings (MayRequire filtering, source context, ...),
+//! so a cache written with different settings or before a `CACHE_VERSION` bump is discarded.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{write_output, RimWorldDef};
+
+/// Part of every cache's settings. Bump it whenever a parser change alters the defs parsed
+/// from the same file, so caches written before the change are discarded.
+pub const CACHE_VERSION: u32 = 3;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ParseCache {
+    settings: String,
+    files: HashMap<String, CachedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    sha256: String,
+    defs: Vec<RimWorldDef>,
+}
+
+impl ParseCache {
+    /// Loads the cache at `path` when it exists and was written with `settings`, otherwise
+    /// starts empty. A cache that can't be read is reported and replaced, not an error.
+    pub fn load(path: &Path, settings: String) -> Self {
+        let cache = fs::read(path).ok().map(|raw| -> Result<Self> {
+            Ok(serde_json::from_slice(&zstd::decode_all(raw.as_slice())?)?)
+        });
+
+        match cache {
+            Some(Ok(cache)) if cache.settings == settings => {
+                println!("  ✓ Parse cache loaded: {} files", cache.files.len());
+                return cache;
+            }
+            Some(Ok(_)) => println!("  ⚠ Parse cache was written with other settings, reparsing everything"),
+            Some(Err(e)) => println!("  ⚠ Parse cache unreadable ({}), reparsing everything", e),
+            None => println!("  ✓ No parse cache yet, it will be created"),
+        }
+        Self { settings, files: HashMap::new() }
+    }
+
+    /// The cached defs of `file_path` when its content hash is unchanged.
+    pub fn lookup(&self, file_path: &Path, sha256: &str) -> Option<Vec<RimWorldDef>> {
+        let cached = self.files.get(file_path.to_string_lossy().as_ref())?;
+        (cached.sha256 == sha256).then(|| cached.defs.clone())
+    }
+
+    /// A cache holding exactly the given files as (path, content hash, defs), so files
+    /// removed since the last run drop out.
+    pub fn rebuild(&self, files: Vec<(PathBuf, String, Vec<RimWorldDef>)>) -> Self {
+        Self {
+            settings: self.settings.clone(),
+            files: files.into_iter()
+                .map(|(path, sha256, defs)| (path.to_string_lossy().to_string(), CachedFile { sha256, defs }))
+                .collect(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec(self)?;
+        write_output(path, zstd::encode_all(json.as_slice(), 3)?)?;
+        println!("  ✓ Parse cache written: {} ({} files)", path.display(), self.files.len());
+        Ok(())
+    }
+}
+
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
@@ -0,0 +1,86 @@
+//! Flat def index for spreadsheets (`--format csv`): one row per def in `defs.csv`, with
+//! the columns in `HEADER`. Tags are joined with `;`, and fields containing commas,
+//! quotes or line breaks are quoted by the writer.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::{compare_defs, create_output_file, RimWorldDef};
+
+const HEADER: [&str; 10] = [
+    "def_name",
+    "def_type",
+    "extension",
+    "label",
+    "parent_name",
+    "is_abstract",
+    "file_path",
+    "tags",
+    "references_out_count",
+    "references_in_count",
+];
+
+pub fn write_csv(defs: &[RimWorldDef], path: &Path) -> Result<()> {
+    println!("\nWriting CSV index...");
+
+    let mut sorted_defs: Vec<&RimWorldDef> = defs.iter().collect();
+    sorted_defs.sort_by(|a, b| compare_defs(a, b));
+
+    let mut writer = csv::Writer::from_writer(create_output_file(path)?);
+    writer.write_record(HEADER)?;
+    for def in &sorted_defs {
+        writer.write_record([
+            def.def_name.as_str(),
+            def.def_type.as_str(),
+            def.extension.as_str(),
+            def.label.as_deref().unwrap_or(""),
+            def.parent_name.as_deref().unwrap_or(""),
+            if def.is_abstract { "true" } else { "false" },
+            def.file_path.as_str(),
+            def.tags.join(";").as_str(),
+            def.references_out.len().to_string().as_str(),
+            def.references_in.len().to_string().as_str(),
+        ])?;
+    }
+    writer.flush()?;
+
+    println!("  ✓ {} definitions written: {}", sorted_defs.len(), path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+
+    #[test]
+    fn rows_survive_quoting_and_parse_back() {
+        let mut defs = DefParser::new(String::new()).parse_xml_str(r#"<Defs>
+            <ThingDef ParentName="ResourceBase">
+                <defName>Steel</defName>
+                <label>steel, "refined"
+bars</label>
+            </ThingDef>
+            <ThingDef><defName>Gold</defName></ThingDef>
+        </Defs>"#, Path::new("Data/Core/Defs/Items.xml")).unwrap();
+        defs[0].tags = vec!["Resource".to_string(), "Has Parent".to_string()];
+        defs[0].references_out = vec!["ResourceBase".to_string()];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("defs.csv");
+        write_csv(&defs, &path).unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(reader.headers().unwrap(), &csv::StringRecord::from(HEADER.to_vec()));
+        let rows: Vec<csv::StringRecord> = reader.records().map(|row| row.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "Gold");
+        assert_eq!(&rows[0][3], "");
+        assert_eq!(&rows[1][0], "Steel");
+        assert_eq!(&rows[1][3], "steel, \"refined\"\nbars");
+        assert_eq!(&rows[1][4], "ResourceBase");
+        assert_eq!(&rows[1][7], "Resource;Has Parent");
+        assert_eq!(rows[1][7].split(';').collect::<Vec<_>>(), ["Resource", "Has Parent"]);
+        assert_eq!(&rows[1][8], "1");
+    }
+}
@@ -0,0 +1,189 @@
+//! Serde model of a generated dataset, for reading one back (`convert`, `--prior-dataset`,
+//! `--delta-base`). Fields the model doesn't name, such as the structured extractions, are
+//! kept in `extra`, so a dataset that is read and written again keeps every field.
+
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crate::{DatasetGenerator, DefParser, RimWorldDef, FORMAT_VERSION};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dataset {
+    #[serde(default)]
+    pub format_version: u32,
+    pub categories: Vec<Category>,
+    #[serde(default)]
+    pub stats: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Category {
+    pub name: String,
+    pub display_name: String,
+    pub count: usize,
+    pub definitions: Vec<DatasetDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetDef {
+    pub def_name: String,
+    pub def_type: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parent_name: Option<String>,
+    #[serde(default)]
+    pub is_abstract: bool,
+    #[serde(default)]
+    pub file_path: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub elements: Vec<Value>,     // Flattened elements as written by the generator
+    #[serde(default)]
+    pub references_out: Vec<String>,
+    #[serde(default)]
+    pub references_in: Vec<String>,
+    #[serde(default)]
+    pub code_references: Vec<String>,
+    #[serde(default)]
+    pub raw_xml: String,
+    #[serde(default)]
+    pub extension: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl DatasetDef {
+    /// (def_type, def_name), which identifies a def across datasets.
+    pub fn key(&self) -> (String, String) {
+        (self.def_type.clone(), self.def_name.clone())
+    }
+
+    /// The def as the parser produced it, for the exports that work on parsed defs. The
+    /// element tree is parsed again from `raw_xml`; what was computed across the whole
+    /// install (references, tags, extension, mod) is taken from the dataset.
+    pub fn to_def(&self) -> Result<RimWorldDef> {
+        let parser = DefParser::new(String::new());
+        let mut def = parser.parse_xml_str(&format!("<Defs>{}</Defs>", self.raw_xml), Path::new(&self.file_path))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("{} {}: raw_xml holds no definition", self.def_type, self.def_name))?;
+
+        let extra = |key: &str| self.extra.get(key).cloned().unwrap_or(Value::Null);
+        def.def_name = self.def_name.clone();
+        def.def_type = self.def_type.clone();
+        def.label = self.label.clone();
+        def.description = self.description.clone();
+        def.parent_name = self.parent_name.clone();
+        def.is_abstract = self.is_abstract;
+        def.file_path = self.file_path.clone();
+        def.tags = self.tags.clone();
+        def.references_out = self.references_out.clone();
+        def.references_in = self.references_in.clone();
+        def.code_references = self.code_references.clone();
+        def.extension = self.extension.clone();
+        def.references = serde_json::from_value(extra("references")).unwrap_or_default();
+        def.inherit_name = serde_json::from_value(extra("inherit_name")).unwrap_or_default();
+        def.source_mod = serde_json::from_value(extra("source_mod")).unwrap_or_default();
+        def.mod_name = serde_json::from_value(extra("mod_name")).unwrap_or_default();
+        Ok(def)
+    }
+}
+
+impl Dataset {
+    /// Reads a single-file dataset, compressed with zstd (`.zstd`, `.zst`), gzip (`.gz`)
+    /// or plain JSON. A `--flat` array is grouped into categories by def type and checked
+    /// against the version in its `.stats.json` sidecar, if that is present. Datasets of
+    /// another format version are rejected.
+    pub fn read(path: &Path) -> Result<Self> {
+        let raw = fs::read(path)?;
+        let json_data = match path.extension().and_then(|e| e.to_str()) {
+            Some("zstd") | Some("zst") => zstd::decode_all(raw.as_slice())?,
+            Some("gz") => {
+                let mut json_data = Vec::new();
+                GzDecoder::new(raw.as_slice()).read_to_end(&mut json_data)?;
+                json_data
+            }
+            _ => raw,
+        };
+
+        let dataset = match serde_json::from_slice::<Value>(&json_data)? {
+            Value::Array(defs) => {
+                let defs = defs.into_iter()
+                    .map(serde_json::from_value)
+                    .collect::<Result<Vec<DatasetDef>, _>>()?;
+                let mut dataset = Self::from_defs(defs);
+                if let Some(sidecar) = Self::read_flat_stats(path)? {
+                    dataset.format_version = sidecar["format_version"].as_u64().unwrap_or(0) as u32;
+                    dataset.stats = sidecar["stats"].clone();
+                }
+                dataset
+            }
+            dataset => serde_json::from_value(dataset)?,
+        };
+
+        if dataset.format_version != FORMAT_VERSION {
+            return Err(anyhow::anyhow!(
+                "{}: dataset format version {} is not supported, expected {}; regenerate it with this version",
+                path.display(),
+                dataset.format_version,
+                FORMAT_VERSION
+            ));
+        }
+        Ok(dataset)
+    }
+
+    /// The `--flat` stats sidecar next to `path` (`dataset.stats.json` for
+    /// `dataset.json.zstd`), or None when there is none.
+    fn read_flat_stats(path: &Path) -> Result<Option<Value>> {
+        let base = match path.extension().and_then(|e| e.to_str()) {
+            Some("zstd") | Some("zst") | Some("gz") => path.with_extension(""),
+            _ => path.to_path_buf(),
+        };
+        let sidecar = base.with_extension("stats.json");
+        if !sidecar.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(sidecar)?)?))
+    }
+
+    fn from_defs(defs: Vec<DatasetDef>) -> Self {
+        let mut grouped: BTreeMap<String, Vec<DatasetDef>> = BTreeMap::new();
+        for def in defs {
+            grouped.entry(def.def_type.clone()).or_default().push(def);
+        }
+
+        let mut categories: Vec<Category> = grouped.into_iter()
+            .map(|(name, definitions)| Category {
+                display_name: DatasetGenerator::format_category_name(&name),
+                count: definitions.len(),
+                name,
+                definitions,
+            })
+            .collect();
+        categories.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+
+        Self {
+            format_version: FORMAT_VERSION,
+            categories,
+            stats: Value::Null,
+        }
+    }
+
+    pub fn defs(&self) -> impl Iterator<Item = &DatasetDef> {
+        self.categories.iter().flat_map(|category| &category.definitions)
+    }
+
+    pub fn into_defs(self) -> impl Iterator<Item = DatasetDef> {
+        self.categories.into_iter().flat_map(|category| category.definitions)
+    }
+}
@@ -0,0 +1,115 @@
+//! Detection of element tags the game has stopped reading, driven by a TOML table
+//! (see `deprecated-tags.toml` for the format and the built-in entries).
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::{DefElement, RimWorldDef};
+
+const DEFAULT_TABLE: &str = include_str!("../deprecated-tags.toml");
+
+#[derive(Debug, Deserialize)]
+pub struct DeprecationTable {
+    #[serde(rename = "tag", default)]
+    tags: Vec<DeprecatedTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeprecatedTag {
+    name: String,
+    parent: Option<String>,
+    replacement: String,
+    since: Option<String>,
+}
+
+impl DeprecationTable {
+    /// Loads the table from `path`, or the built-in table when no path is given.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let table = match path {
+            Some(path) => toml::from_str(&fs::read_to_string(path)?)?,
+            None => toml::from_str(DEFAULT_TABLE)?,
+        };
+        Ok(table)
+    }
+
+    /// Describes every deprecated tag used in the def, with its replacement.
+    pub fn check(&self, def: &RimWorldDef) -> Vec<String> {
+        let mut findings = Vec::new();
+        self.check_recursive(def, &def.def_type, &def.elements, &mut findings);
+        findings
+    }
+
+    fn check_recursive(&self, def: &RimWorldDef, parent: &str, elements: &[DefElement], findings: &mut Vec<String>) {
+        for element in elements {
+            let deprecated = self.tags.iter().find(|tag| {
+                tag.name == element.name && tag.parent.as_deref().is_none_or(|p| p == parent)
+            });
+
+            if let Some(tag) = deprecated {
+                let since = tag.since.as_deref().map(|v| format!(" since {}", v)).unwrap_or_default();
+                findings.push(format!("{} \"{}\" ({}): <{}><{}> is deprecated{}, use {} instead",
+                    def.def_type, def.def_name, def.file_path, parent, element.name, since, tag.replacement));
+            }
+
+            self.check_recursive(def, &element.name, &element.children, findings);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+
+    fn parse(xml: &str) -> Vec<RimWorldDef> {
+        DefParser::new(String::new()).parse_xml_str(xml, Path::new("Data/Core/Defs/Test.xml")).unwrap()
+    }
+
+    #[test]
+    fn built_in_table_only_flags_tags_under_their_parent() {
+        let defs = parse(r#"<Defs>
+            <ThingDef>
+                <defName>Muffalo</defName>
+                <race><wildness>0.6</wildness></race>
+                <statBases><wildness>0.6</wildness></statBases>
+            </ThingDef>
+        </Defs>"#);
+
+        let findings = DeprecationTable::load(None).unwrap().check(&defs[0]);
+        assert_eq!(findings, [
+            "ThingDef \"Muffalo\" (Data/Core/Defs/Test.xml): <race><wildness> is deprecated since 1.6, use statBases.Wildness instead",
+        ]);
+    }
+
+    #[test]
+    fn custom_tables_match_top_level_fields_and_any_parent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deprecated.toml");
+        fs::write(&path, r#"
+            [[tag]]
+            name = "oldLabel"
+            parent = "ThingDef"
+            replacement = "label"
+
+            [[tag]]
+            name = "legacyFlag"
+            replacement = "flags"
+            since = "1.4"
+        "#).unwrap();
+        let table = DeprecationTable::load(Some(&path)).unwrap();
+
+        let defs = parse(r#"<Defs>
+            <ThingDef>
+                <defName>Steel</defName>
+                <oldLabel>steel</oldLabel>
+                <comps><li><legacyFlag>true</legacyFlag><oldLabel>x</oldLabel></li></comps>
+            </ThingDef>
+        </Defs>"#);
+        assert_eq!(table.check(&defs[0]), [
+            "ThingDef \"Steel\" (Data/Core/Defs/Test.xml): <ThingDef><oldLabel> is deprecated, use label instead",
+            "ThingDef \"Steel\" (Data/Core/Defs/Test.xml): <li><legacyFlag> is deprecated since 1.4, use flags instead",
+        ]);
+    }
+}
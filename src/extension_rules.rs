@@ -0,0 +1,98 @@
+//! Path-based rules assigning each def file to an extension (Core, a DLC, a mod...),
+//! loaded from TOML (see `extension-rules.toml` for the format and the built-in rules).
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_RULES: &str = include_str!("../extension-rules.toml");
+
+#[derive(Debug, Deserialize)]
+pub struct ExtensionRules {
+    #[serde(rename = "rule", default)]
+    rules: Vec<ExtensionRule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExtensionRule {
+    path_contains: Option<String>,
+    path_prefix: Option<String>,
+    extension: String,
+}
+
+impl ExtensionRules {
+    /// Loads the rules from `path`, or the built-in rules when no path is given.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let rules: Self = match path {
+            Some(path) => toml::from_str(&fs::read_to_string(path)?)?,
+            None => toml::from_str(DEFAULT_RULES)?,
+        };
+
+        if let Some(rule) = rules.rules.iter().find(|rule| rule.path_contains.is_none() && rule.path_prefix.is_none()) {
+            return Err(anyhow::anyhow!("Extension rule for {} needs path_contains or path_prefix", rule.extension));
+        }
+        Ok(rules)
+    }
+
+    /// Extension of the first rule matching the file, given its full path and its
+    /// path relative to the RimWorld directory.
+    pub fn detect(&self, full_path: &str, relative_path: &str) -> Option<&str> {
+        let full_path = full_path.replace('\\', "/").to_lowercase();
+        let relative_path = relative_path.replace('\\', "/").to_lowercase();
+
+        self.rules.iter()
+            .find(|rule| {
+                rule.path_contains.as_ref().is_none_or(|part| full_path.contains(&part.to_lowercase()))
+                    && rule.path_prefix.as_ref().is_none_or(|prefix| relative_path.starts_with(&prefix.to_lowercase()))
+            })
+            .map(|rule| rule.extension.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_rules_map_nonstandard_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rules.toml");
+        fs::write(&path, r#"
+            [[rule]]
+            path_prefix = "Overhauls/Medieval/"
+            extension = "Medieval"
+
+            [[rule]]
+            path_contains = "core"
+            extension = "Core"
+        "#).unwrap();
+        let rules = ExtensionRules::load(Some(&path)).unwrap();
+
+        assert_eq!(rules.detect("/games/RimWorld/Overhauls/Medieval/Defs/Swords.xml", "Overhauls/Medieval/Defs/Swords.xml"), Some("Medieval"));
+        assert_eq!(rules.detect("C:\\RimWorld\\overhauls\\medieval\\Defs\\Core.xml", "overhauls\\medieval\\Defs\\Core.xml"), Some("Medieval"));
+        assert_eq!(rules.detect("/games/RimWorld/Data/Core/Defs/Items.xml", "Data/Core/Defs/Items.xml"), Some("Core"));
+        assert_eq!(rules.detect("/games/RimWorld/Data/Biotech/Defs/Genes.xml", "Data/Biotech/Defs/Genes.xml"), None);
+
+        fs::write(&path, "[[rule]]\nextension = \"Broken\"\n").unwrap();
+        assert!(ExtensionRules::load(Some(&path)).unwrap_err().to_string().contains("Broken needs path_contains or path_prefix"));
+    }
+
+
+    #[test]
+    fn built_in_rules_map_official_content_folders() {
+        let rules = ExtensionRules::load(None).unwrap();
+        let detect = |path: &str| rules.detect(&format!("/games/RimWorld/{}", path), path);
+
+        assert_eq!(detect("Data/Core/Defs/Items.xml"), Some("Core"));
+        assert_eq!(detect("Data/Royalty/Defs/Titles.xml"), Some("Royalty"));
+        assert_eq!(detect("Data/Ideology/Defs/Memes.xml"), Some("Ideology"));
+        assert_eq!(detect("Data/Biotech/Defs/Genes.xml"), Some("Biotech"));
+        assert_eq!(detect("Data/Anomaly/Defs/Entities.xml"), Some("Anomaly"));
+        assert_eq!(detect("Data/Odyssey/Defs/Ships.xml"), Some("Odyssey"));
+        assert_eq!(detect("DATA\\BIOTECH\\Defs\\Genes.xml"), Some("Biotech"));
+        // Earlier rules win, so a Biotech file named after Core stays Biotech
+        assert_eq!(detect("Data/Biotech/Defs/CoreGenes.xml"), Some("Biotech"));
+        assert_eq!(detect("Mods/Vehicles/Defs/Cars.xml"), None);
+    }
+}
@@ -0,0 +1,1238 @@
+//! Structured extraction of well-known def sub-structures (race data, recipes, ...).
+//! Each extraction reads the def's root element and yields a typed view that is emitted
+//! alongside the generic element tree, plus the def names it is known to reference.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::DefElement;
+
+/// `<building>` fields whose value is the name of another def.
+const BUILDING_REFERENCE_FIELDS: &[&str] = &[
+    "turretGunDef",
+    "mineableThing",
+    "smoothedThing",
+    "unsmoothedThing",
+    "leaveTerrain",
+    "naturalTerrain",
+    "soundAmbient",
+    "soundDoorOpenPowered",
+    "soundDoorClosePowered",
+    "soundDoorOpenManual",
+    "soundDoorCloseManual",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StructuredFields {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub growth: Option<Growth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipe_maker: Option<RecipeMaker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Tool>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub graphic: Option<Graphic>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub building: Option<BTreeMap<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abilities: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ability: Option<Ability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hediffs_applied: Option<Vec<HediffApplication>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sounds: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plant: Option<Plant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub race: Option<Race>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comps: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ideo: Option<Ideo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stat_modifiers: Option<StatModifiers>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skill_requirements: Option<Vec<SkillRequirement>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drops: Option<Drops>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thing_set_maker_tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade: Option<Trade>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<Coverage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_parts: Option<BodyPart>,
+}
+
+impl StructuredFields {
+    pub fn extract(root: &DefElement) -> Self {
+        Self {
+            growth: extract_growth(root),
+            recipe_maker: extract_recipe_maker(root),
+            tools: extract_tools(root),
+            graphic: extract_graphic(root),
+            building: extract_building(root),
+            abilities: extract_abilities(root),
+            ability: extract_ability(root),
+            hediffs_applied: extract_hediffs_applied(root),
+            sounds: extract_sounds(root),
+            plant: extract_plant(root),
+            race: extract_race(root),
+            comps: comps(&root.children),
+            ideo: extract_ideo(root),
+            stat_modifiers: extract_stat_modifiers(root),
+            skill_requirements: extract_skill_requirements(root),
+            drops: extract_drops(root),
+            thing_set_maker_tags: root.find_child("thingSetMakerTags")
+                .map(list_contents)
+                .filter(|tags| !tags.is_empty()),
+            trade: extract_trade(root),
+            coverage: extract_coverage(root),
+            body_parts: extract_body_parts(root),
+        }
+    }
+
+    /// Def names referenced by the extracted structures.
+    pub fn references(&self) -> Vec<String> {
+        let mut references = Vec::new();
+
+        if let Some(growth) = &self.growth {
+            references.extend(growth.life_stages.iter().map(|stage| stage.def.clone()));
+        }
+
+        if let Some(recipe_maker) = &self.recipe_maker {
+            references.extend(recipe_maker.work_skill.iter().cloned());
+            references.extend(recipe_maker.skill_requirements.iter().map(|req| req.skill.clone()));
+            references.extend(recipe_maker.recipe_users.iter().cloned());
+            references.extend(recipe_maker.research_prerequisites.iter().cloned());
+        }
+
+        for (field, value) in self.building.iter().flatten() {
+            if BUILDING_REFERENCE_FIELDS.contains(&field.as_str()) {
+                references.extend(value.as_str().map(|v| v.to_string()));
+            }
+        }
+
+        references.extend(self.abilities.iter().flatten().cloned());
+
+        if let Some(ability) = &self.ability {
+            references.extend(ability.hediffs.iter().cloned());
+        }
+
+        references.extend(self.hediffs_applied.iter().flatten().map(|applied| applied.hediff.clone()));
+        references.extend(self.sounds.iter().flatten().map(|(_, sound)| sound.clone()));
+
+        if let Some(race) = &self.race {
+            references.extend(race.body.iter().cloned());
+            references.extend(race.flesh_type.iter().cloned());
+        }
+
+        references.extend(self.skill_requirements.iter().flatten().map(|req| req.skill.clone()));
+
+        if let Some(drops) = &self.drops {
+            references.extend(drops.butcher_products.keys().cloned());
+            references.extend(drops.killed_leavings.keys().cloned());
+        }
+
+        if let Some(modifiers) = &self.stat_modifiers {
+            references.extend(modifiers.equipped_stat_offsets.keys().cloned());
+            references.extend(modifiers.stat_offsets.keys().cloned());
+            references.extend(modifiers.stat_factors.keys().cloned());
+        }
+
+        if let Some(ideo) = &self.ideo {
+            references.extend(ideo.precepts.iter().cloned());
+            references.extend(ideo.memes.iter().cloned());
+            references.extend(ideo.ritual_patterns.iter().cloned());
+            references.extend(ideo.ritual_outcome.iter().cloned());
+            references.extend(ideo.ritual_behavior.iter().cloned());
+        }
+
+        if let Some(plant) = &self.plant {
+            references.extend(plant.harvested_thing.iter().cloned());
+        }
+
+        for tool in self.tools.iter().flatten() {
+            references.extend(tool.capacities.iter().cloned());
+            references.extend(tool.linked_body_parts_group.iter().cloned());
+        }
+
+        if let Some(coverage) = &self.coverage {
+            references.extend(coverage.body_part_groups.iter().cloned());
+            references.extend(coverage.layers.iter().cloned());
+            references.extend(coverage.verb_body_part_groups.iter().cloned());
+        }
+
+        // Walk the body tree without recursion, the parse depth bounds it anyway
+        let mut parts: Vec<&BodyPart> = self.body_parts.iter().collect();
+        while let Some(part) = parts.pop() {
+            references.push(part.def.clone());
+            references.extend(part.groups.iter().cloned());
+            parts.extend(&part.parts);
+        }
+
+        references
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Growth {
+    pub life_stages: Vec<LifeStage>,
+    pub body_size: Option<f32>,
+    pub wildness: Option<f32>,
+    pub gestation_days: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifeStage {
+    pub def: String,       // LifeStageDef name
+    pub min_age: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeMaker {
+    pub work_amount: Option<f32>,
+    pub work_skill: Option<String>,             // SkillDef name
+    pub skill_requirements: Vec<SkillRequirement>,
+    pub recipe_users: Vec<String>,              // Workbench ThingDef names
+    pub research_prerequisites: Vec<String>,    // ResearchProjectDef names
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillRequirement {
+    pub skill: String,                          // SkillDef name
+    pub min_level: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    pub label: Option<String>,
+    pub power: Option<f32>,
+    pub cooldown: Option<f32>,
+    pub capacities: Vec<String>,                    // ToolCapacityDef names
+    pub linked_body_parts_group: Option<String>,    // BodyPartGroupDef name
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Graphic {
+    pub graphic_class: Option<String>,
+    pub tex_path: Option<String>,
+    pub tex_path_kind: TexPathKind,
+    pub draw_size: Option<Vec<f32>>,
+    pub atlas: bool,                // Linked graphics (walls, conduits) use a texture atlas
+}
+
+/// How the game resolves `texPath`, which depends on the graphic class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TexPathKind {
+    File,           // A single texture
+    Directional,    // `_north`/`_east`/`_south`/`_west` variants of one path
+    Folder,         // A directory of variants
+    Unknown,
+}
+
+/// AbilityDef specifics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ability {
+    pub cooldown_ticks: Option<String>,     // Either a number or a `min~max` range
+    pub range: Option<f32>,
+    pub targets: Vec<String>,               // Enabled targetParams flags, e.g. canTargetPawns
+    pub hediffs: Vec<String>,               // HediffDef names applied by the ability's comps
+}
+
+/// Properties of a pawn's `<race>` block that tell humanlikes, animals and mechanoids apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Race {
+    pub intelligence: Option<String>,       // Humanlike, ToolUser or Animal
+    pub food_type: Vec<String>,             // Flags such as OmnivoreHuman, split on commas
+    pub flesh_type: Option<String>,         // FleshTypeDef, e.g. Mechanoid
+    pub body: Option<String>,               // BodyDef
+    pub kind: PawnKind,
+}
+
+/// What kind of pawn a race makes, for filtering pawns and animals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PawnKind {
+    Humanlike,
+    Animal,
+    Mechanoid,
+    Other,      // ToolUser races that aren't mechanoids
+}
+
+/// Everything a trade screen needs about a thing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub tradeability: String,               // All, Buyable, Sellable or None; All when not set
+    pub trade_tags: Vec<String>,
+    pub market_value: Option<f32>,          // statBases.MarketValue
+    pub is_currency: bool,                  // Silver, the only currency the game knows
+}
+
+/// What an animal or building leaves behind, as ThingDef -> count.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Drops {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub butcher_products: BTreeMap<String, u32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub killed_leavings: BTreeMap<String, u32>,
+    pub leaves_resources_when_killed: bool, // Part of the costList is dropped on destruction
+}
+
+/// How the def changes the stats of whoever wears, wields or has it, as StatDef -> value.
+/// Unlike `statBases` these don't describe the def itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatModifiers {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub equipped_stat_offsets: BTreeMap<String, f32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub stat_offsets: BTreeMap<String, f32>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub stat_factors: BTreeMap<String, f32>,
+}
+
+/// Links between ideology defs: memes to the precepts they require, precepts to their
+/// memes and ritual patterns, ritual patterns to their outcome and behavior.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ideo {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub precepts: Vec<String>,              // MemeDef requireOne, flattened
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub memes: Vec<String>,                 // PreceptDef associatedMemes and requiredMemes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ritual_patterns: Vec<String>,       // PreceptDef ritualPatternBase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ritual_outcome: Option<String>,     // RitualPatternDef ritualOutcomeEffect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ritual_behavior: Option<String>,    // RitualPatternDef ritualBehavior
+}
+
+/// Farming data from a plant's `<plant>` block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plant {
+    pub grow_days: Option<f32>,
+    pub harvested_thing: Option<String>,    // None for decorative plants
+    pub harvest_yield: Option<f32>,
+    pub fertility_min: Option<f32>,
+    pub sow_tags: Vec<String>,
+}
+
+/// Which body part groups a def works on: the groups and layers apparel covers, and the
+/// groups its verbs are linked to via `linkedBodyPartsGroup`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Coverage {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub body_part_groups: Vec<String>,      // BodyPartGroupDefs in apparel.bodyPartGroups
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<String>,                // ApparelLayerDefs in apparel.layers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub verb_body_part_groups: Vec<String>, // BodyPartGroupDefs linked by verbs
+}
+
+/// A part of a BodyDef's tree, starting at its `corePart`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyPart {
+    pub def: String,                        // BodyPartDef name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_label: Option<String>,       // Tells apart left and right parts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<f32>,              // Share of the parent part's area
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,                // BodyPartGroupDef names
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<BodyPart>,
+}
+
+/// A HediffDef the def applies, with the top-level field it was found under
+/// (`hediffGivers`, `ingestible`, `comps`, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HediffApplication {
+    pub hediff: String,
+    pub via: String,
+}
+
+/// Numeric content at a dotted path below `element`.
+fn content_f32(element: &DefElement, path: &str) -> Option<f32> {
+    element.get_content(path).and_then(|v| v.trim().parse().ok())
+}
+
+/// JSON value of an element: booleans and numbers are typed, lists become arrays
+/// and nested elements objects.
+pub fn typed_value(element: &DefElement) -> Value {
+    if element.children.is_empty() {
+        return match element.content.as_deref().map(str::trim) {
+            None => Value::Null,
+            Some(v) if v.eq_ignore_ascii_case("true") => Value::Bool(true),
+            Some(v) if v.eq_ignore_ascii_case("false") => Value::Bool(false),
+            Some(v) => crate::normalize_number(v)
+                .and_then(|n| n.parse::<f64>().ok())
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .unwrap_or_else(|| Value::String(v.to_string())),
+        };
+    }
+
+    if element.children.iter().all(|c| c.name == "li") {
+        return Value::Array(element.children.iter().map(typed_value).collect());
+    }
+
+    Value::Object(element.children.iter()
+        .map(|c| (c.name.clone(), typed_value(c)))
+        .collect())
+}
+
+/// Contents of the `<li>` entries of a list element.
+fn list_contents(element: &DefElement) -> Vec<String> {
+    element.children.iter()
+        .filter(|c| c.name == "li")
+        .filter_map(|c| c.content.clone())
+        .collect()
+}
+
+/// Reads `<skillRequirements>` in either the `<Crafting>6</Crafting>` or the
+/// `<li><skill>Crafting</skill><minLevel>6</minLevel></li>` form.
+fn skill_requirements(element: &DefElement) -> Vec<SkillRequirement> {
+    element.children.iter()
+        .filter_map(|c| {
+            if c.name == "li" {
+                Some(SkillRequirement {
+                    skill: c.get_content("skill")?.to_string(),
+                    min_level: c.get_content("minLevel").and_then(|v| v.trim().parse().ok()),
+                })
+            } else {
+                Some(SkillRequirement {
+                    skill: c.name.clone(),
+                    min_level: c.content.as_deref().and_then(|v| v.trim().parse().ok()),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Life stages and growth-related race properties of pawn ThingDefs.
+fn extract_growth(root: &DefElement) -> Option<Growth> {
+    let race = root.find_child("race")?;
+
+    let life_stages: Vec<LifeStage> = race.find_child("lifeStageAges")
+        .map(|ages| ages.children.iter()
+            .filter_map(|li| Some(LifeStage {
+                def: li.get_content("def")?.to_string(),
+                min_age: content_f32(li, "minAge"),
+            }))
+            .collect())
+        .unwrap_or_default();
+
+    // Wildness moved from <race> to statBases in newer versions
+    let wildness = content_f32(race, "wildness")
+        .or_else(|| content_f32(root, "statBases.Wildness"));
+
+    let growth = Growth {
+        life_stages,
+        body_size: content_f32(race, "baseBodySize"),
+        wildness,
+        gestation_days: content_f32(race, "gestationPeriodDays"),
+    };
+
+    if growth.life_stages.is_empty() && growth.body_size.is_none()
+        && growth.wildness.is_none() && growth.gestation_days.is_none() {
+        return None;
+    }
+
+    Some(growth)
+}
+
+/// The inline recipe of ThingDefs made at a workbench.
+fn extract_recipe_maker(root: &DefElement) -> Option<RecipeMaker> {
+    let recipe_maker = root.find_child("recipeMaker")?;
+
+    // Work is usually given as the WorkToMake stat rather than on the recipeMaker itself
+    let work_amount = content_f32(recipe_maker, "workAmount")
+        .or_else(|| content_f32(root, "statBases.WorkToMake"));
+
+    let mut research_prerequisites: Vec<String> = recipe_maker.get_content("researchPrerequisite")
+        .map(|r| r.to_string())
+        .into_iter()
+        .collect();
+    if let Some(prerequisites) = recipe_maker.find_child("researchPrerequisites") {
+        research_prerequisites.extend(list_contents(prerequisites));
+    }
+
+    Some(RecipeMaker {
+        work_amount,
+        work_skill: recipe_maker.get_content("workSkill").map(|s| s.to_string()),
+        skill_requirements: recipe_maker.find_child("skillRequirements")
+            .map(skill_requirements)
+            .unwrap_or_default(),
+        recipe_users: recipe_maker.find_child("recipeUsers")
+            .map(list_contents)
+            .unwrap_or_default(),
+        research_prerequisites,
+    })
+}
+
+/// Melee attacks of weapons and pawns.
+fn extract_tools(root: &DefElement) -> Option<Vec<Tool>> {
+    let tools: Vec<Tool> = root.find_child("tools")?.children.iter()
+        .filter(|li| li.name == "li")
+        .map(|li| Tool {
+            label: li.get_content("label").map(|v| v.to_string()),
+            power: content_f32(li, "power"),
+            cooldown: content_f32(li, "cooldownTime"),
+            capacities: li.find_child("capacities").map(list_contents).unwrap_or_default(),
+            linked_body_parts_group: li.get_content("linkedBodyPartsGroup").map(|v| v.to_string()),
+        })
+        .collect();
+
+    if tools.is_empty() {
+        return None;
+    }
+
+    Some(tools)
+}
+
+fn tex_path_kind(graphic_class: Option<&str>) -> TexPathKind {
+    match graphic_class {
+        None | Some("Graphic_Single") => TexPathKind::File,
+        Some("Graphic_Multi") => TexPathKind::Directional,
+        Some("Graphic_Random" | "Graphic_StackCount" | "Graphic_Appearances" | "Graphic_Flicker"
+            | "Graphic_Cluster" | "Graphic_ClusterTight" | "Graphic_Indexed" | "Graphic_MealVariants") => TexPathKind::Folder,
+        Some(_) => TexPathKind::Unknown,
+    }
+}
+
+/// Parses `(2,2)` or `2` style sizes.
+fn parse_vector(value: &str) -> Option<Vec<f32>> {
+    value.trim().trim_start_matches('(').trim_end_matches(')')
+        .split(',')
+        .map(|v| v.trim().parse().ok())
+        .collect()
+}
+
+/// The def's `graphicData`: which class draws it and how its texPath is interpreted.
+fn extract_graphic(root: &DefElement) -> Option<Graphic> {
+    let graphic_data = root.find_child("graphicData")?;
+    let graphic_class = graphic_data.get_content("graphicClass").map(|v| v.to_string());
+
+    Some(Graphic {
+        tex_path_kind: tex_path_kind(graphic_class.as_deref()),
+        graphic_class,
+        tex_path: graphic_data.get_content("texPath").map(|v| v.to_string()),
+        draw_size: graphic_data.get_content("drawSize").and_then(parse_vector),
+        atlas: graphic_data.get_content("linkType").is_some_and(|link| link != "None"),
+    })
+}
+
+/// The `<building>` block of structures as a map of typed values.
+fn extract_building(root: &DefElement) -> Option<BTreeMap<String, Value>> {
+    let building = root.find_child("building")?;
+
+    Some(building.children.iter()
+        .map(|c| (c.name.clone(), typed_value(c)))
+        .collect())
+}
+
+/// Abilities granted by genes, pawn kinds and the like.
+fn extract_abilities(root: &DefElement) -> Option<Vec<String>> {
+    let abilities = list_contents(root.find_child("abilities")?);
+    if abilities.is_empty() {
+        return None;
+    }
+
+    Some(abilities)
+}
+
+fn extract_ability(root: &DefElement) -> Option<Ability> {
+    if root.name != "AbilityDef" {
+        return None;
+    }
+
+    let verb = root.find_child("verbProperties");
+    let targets = verb
+        .and_then(|verb| verb.find_child("targetParams"))
+        .map(|params| params.children.iter()
+            .filter(|c| c.content.as_deref().is_some_and(|v| v.eq_ignore_ascii_case("true")))
+            .map(|c| c.name.clone())
+            .collect())
+        .unwrap_or_default();
+
+    let hediffs = root.find_child("comps")
+        .map(|comps| comps.find_all("hediffDef").into_iter()
+            .filter_map(|hediff| hediff.content.clone())
+            .collect())
+        .unwrap_or_default();
+
+    Some(Ability {
+        cooldown_ticks: root.get_content("cooldownTicksRange")
+            .or_else(|| root.get_content("cooldownTicks"))
+            .map(|v| v.to_string()),
+        range: verb.and_then(|verb| content_f32(verb, "range")),
+        targets,
+        hediffs,
+    })
+}
+
+/// Tags whose content (or `<li>` entries) name a HediffDef.
+const HEDIFF_FIELDS: &[&str] = &["hediff", "hediffDef", "hediffsToBringOn"];
+
+/// Hediffs applied through hediff givers, ingestion outcomes, comps and the like.
+fn extract_hediffs_applied(root: &DefElement) -> Option<Vec<HediffApplication>> {
+    let mut applied = Vec::new();
+
+    for field in &root.children {
+        let mut hediffs = Vec::new();
+        for name in HEDIFF_FIELDS {
+            if field.name == *name {
+                hediffs.extend(field.content.clone());
+                hediffs.extend(list_contents(field));
+            }
+            for element in field.find_all(name) {
+                hediffs.extend(element.content.clone());
+                hediffs.extend(list_contents(element));
+            }
+        }
+
+        applied.extend(hediffs.into_iter().map(|hediff| HediffApplication {
+            hediff,
+            via: field.name.clone(),
+        }));
+    }
+
+    if applied.is_empty() {
+        return None;
+    }
+
+    Some(applied)
+}
+
+/// SoundDef references (`soundImpact`, `soundInteract`, `soundMeleeHitPawn`, ...) anywhere
+/// in the def, keyed by field name. The first occurrence wins when verbs or tools repeat a field.
+fn extract_sounds(root: &DefElement) -> Option<BTreeMap<String, String>> {
+    let mut sounds = BTreeMap::new();
+    let mut pending: Vec<&DefElement> = root.children.iter().rev().collect();
+
+    while let Some(element) = pending.pop() {
+        pending.extend(element.children.iter().rev());
+
+        if !element.name.starts_with("sound") || !element.children.is_empty() {
+            continue;
+        }
+        // Skips numeric settings that share the prefix
+        let Some(sound) = element.content.as_deref()
+            .filter(|v| v.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|v| v.starts_with(|c: char| c.is_ascii_alphabetic()))
+        else {
+            continue;
+        };
+        sounds.entry(element.name.clone()).or_insert_with(|| sound.to_string());
+    }
+
+    if sounds.is_empty() {
+        return None;
+    }
+
+    Some(sounds)
+}
+
+fn extract_plant(root: &DefElement) -> Option<Plant> {
+    let plant = root.find_child("plant")?;
+
+    Some(Plant {
+        grow_days: content_f32(plant, "growDays"),
+        harvested_thing: plant.get_content("harvestedThingDef").map(|v| v.to_string()),
+        harvest_yield: content_f32(plant, "harvestYield"),
+        fertility_min: content_f32(plant, "fertilityMin"),
+        sow_tags: plant.find_child("sowTags").map(list_contents).unwrap_or_default(),
+    })
+}
+
+fn extract_race(root: &DefElement) -> Option<Race> {
+    race(&root.children)
+}
+
+/// The `<race>` block and the pawn kind it makes. Takes the def's elements so it also works
+/// on an inheritance-resolved tree, as intelligence and flesh type often come from a base.
+pub fn race(elements: &[DefElement]) -> Option<Race> {
+    let race = elements.iter().find(|e| e.name == "race")?;
+    let intelligence = race.get_content("intelligence").map(|v| v.to_string());
+    let flesh_type = race.get_content("fleshType").map(|v| v.to_string());
+
+    Some(Race {
+        kind: pawn_kind(intelligence.as_deref(), flesh_type.as_deref()),
+        intelligence,
+        food_type: race.get_content("foodType")
+            .map(|v| v.split(',').map(|flag| flag.trim().to_string()).filter(|flag| !flag.is_empty()).collect())
+            .unwrap_or_default(),
+        flesh_type,
+        body: race.get_content("body").map(|v| v.to_string()),
+    })
+}
+
+/// Mechanoids are told apart by flesh type since they are ToolUsers; a race without
+/// `intelligence` is an animal, which is the game's default.
+fn pawn_kind(intelligence: Option<&str>, flesh_type: Option<&str>) -> PawnKind {
+    if flesh_type == Some("Mechanoid") {
+        return PawnKind::Mechanoid;
+    }
+    match intelligence {
+        Some("Humanlike") => PawnKind::Humanlike,
+        None | Some("Animal") => PawnKind::Animal,
+        Some(_) => PawnKind::Other,
+    }
+}
+
+/// Comp classes listed in `<comps>`, from each entry's `Class` attribute or `<compClass>`.
+/// Takes the def's elements so it also works on an inheritance-resolved tree.
+pub fn comps(elements: &[DefElement]) -> Option<Vec<String>> {
+    let comps: Vec<String> = elements.iter()
+        .find(|e| e.name == "comps")?
+        .children.iter()
+        .filter_map(|comp| comp.attr("Class").or_else(|| comp.get_content("compClass")))
+        .map(|class| class.to_string())
+        .collect();
+
+    if comps.is_empty() {
+        return None;
+    }
+
+    Some(comps)
+}
+
+fn extract_ideo(root: &DefElement) -> Option<Ideo> {
+    let content = |name: &str| root.get_content(name).map(|v| v.to_string());
+    let list = |name: &str| root.find_child(name).map(list_contents).unwrap_or_default();
+
+    let ideo = match root.name.as_str() {
+        // requireOne is a list of alternatives, each itself a list of precepts
+        "MemeDef" => Ideo {
+            precepts: root.find_child("requireOne")
+                .map(|groups| groups.children.iter().flat_map(list_contents).collect())
+                .unwrap_or_default(),
+            ..Ideo::default()
+        },
+        "PreceptDef" => Ideo {
+            memes: [list("associatedMemes"), list("requiredMemes")].concat(),
+            ritual_patterns: content("ritualPatternBase").into_iter().collect(),
+            ..Ideo::default()
+        },
+        "RitualPatternDef" => Ideo {
+            ritual_outcome: content("ritualOutcomeEffect"),
+            ritual_behavior: content("ritualBehavior"),
+            ..Ideo::default()
+        },
+        _ => return None,
+    };
+
+    Some(ideo)
+}
+
+fn extract_stat_modifiers(root: &DefElement) -> Option<StatModifiers> {
+    let stat_map = |name: &str| -> BTreeMap<String, f32> {
+        root.find_child(name)
+            .map(|stats| stats.children.iter()
+                .filter_map(|stat| Some((stat.name.clone(), stat.content.as_deref()?.trim().parse().ok()?)))
+                .collect())
+            .unwrap_or_default()
+    };
+
+    let modifiers = StatModifiers {
+        equipped_stat_offsets: stat_map("equippedStatOffsets"),
+        stat_offsets: stat_map("statOffsets"),
+        stat_factors: stat_map("statFactors"),
+    };
+
+    if modifiers.equipped_stat_offsets.is_empty() && modifiers.stat_offsets.is_empty() && modifiers.stat_factors.is_empty() {
+        return None;
+    }
+
+    Some(modifiers)
+}
+
+/// The def's own `<skillRequirements>`, as on RecipeDefs. Items made at a bench carry
+/// theirs in `recipe_maker` instead.
+fn extract_skill_requirements(root: &DefElement) -> Option<Vec<SkillRequirement>> {
+    let requirements = skill_requirements(root.find_child("skillRequirements")?);
+    if requirements.is_empty() {
+        return None;
+    }
+
+    Some(requirements)
+}
+
+fn extract_drops(root: &DefElement) -> Option<Drops> {
+    let count_map = |name: &str| -> BTreeMap<String, u32> {
+        root.find_child(name)
+            .map(|things| things.children.iter()
+                .filter_map(|thing| Some((thing.name.clone(), thing.content.as_deref()?.trim().parse().ok()?)))
+                .collect())
+            .unwrap_or_default()
+    };
+
+    let drops = Drops {
+        butcher_products: count_map("butcherProducts"),
+        killed_leavings: count_map("killedLeavings"),
+        leaves_resources_when_killed: root.get_content("leaveResourcesWhenKilled")
+            .is_some_and(|v| v.eq_ignore_ascii_case("true")),
+    };
+
+    if drops.butcher_products.is_empty() && drops.killed_leavings.is_empty() && !drops.leaves_resources_when_killed {
+        return None;
+    }
+
+    Some(drops)
+}
+
+fn extract_trade(root: &DefElement) -> Option<Trade> {
+    if root.name != "ThingDef" {
+        return None;
+    }
+
+    let tradeability = root.get_content("tradeability");
+    let trade_tags = root.find_child("tradeTags").map(list_contents).unwrap_or_default();
+    let market_value = content_f32(root, "statBases.MarketValue");
+    if tradeability.is_none() && trade_tags.is_empty() && market_value.is_none() {
+        return None;
+    }
+
+    Some(Trade {
+        tradeability: tradeability.unwrap_or("All").to_string(),
+        trade_tags,
+        market_value,
+        is_currency: root.get_content("defName") == Some("Silver"),
+    })
+}
+
+fn extract_coverage(root: &DefElement) -> Option<Coverage> {
+    let apparel_list = |name: &str| -> Vec<String> {
+        root.find_child("apparel")
+            .and_then(|apparel| apparel.find_child(name))
+            .map(list_contents)
+            .unwrap_or_default()
+    };
+
+    let mut verb_body_part_groups: Vec<String> = Vec::new();
+    for verb in root.find_child("verbs").iter().flat_map(|verbs| &verbs.children) {
+        if let Some(group) = verb.get_content("linkedBodyPartsGroup")
+            && !verb_body_part_groups.iter().any(|g| g == group) {
+            verb_body_part_groups.push(group.to_string());
+        }
+    }
+
+    let coverage = Coverage {
+        body_part_groups: apparel_list("bodyPartGroups"),
+        layers: apparel_list("layers"),
+        verb_body_part_groups,
+    };
+
+    if coverage.body_part_groups.is_empty() && coverage.layers.is_empty() && coverage.verb_body_part_groups.is_empty() {
+        return None;
+    }
+
+    Some(coverage)
+}
+
+/// The part tree of a BodyDef.
+fn extract_body_parts(root: &DefElement) -> Option<BodyPart> {
+    if root.name != "BodyDef" {
+        return None;
+    }
+
+    body_part(root.find_child("corePart")?)
+}
+
+fn body_part(element: &DefElement) -> Option<BodyPart> {
+    Some(BodyPart {
+        def: element.get_content("def")?.to_string(),
+        custom_label: element.get_content("customLabel").map(|v| v.to_string()),
+        coverage: content_f32(element, "coverage"),
+        groups: element.find_child("groups").map(list_contents).unwrap_or_default(),
+        parts: element.find_child("parts")
+            .map(|parts| parts.children.iter().filter_map(body_part).collect())
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+    use std::path::Path;
+
+    /// The structured fields of the first def in the Defs document.
+    fn structured(xml: &str) -> StructuredFields {
+        DefParser::new(String::new()).parse_xml_str(xml, Path::new("Data/Core/Defs/Test.xml")).unwrap()
+            .remove(0)
+            .structured
+    }
+
+    #[test]
+    fn race_kinds() {
+        let animal = structured(r#"<Defs><ThingDef><defName>Muffalo</defName><race>
+            <body>QuadrupedAnimalWithHooves</body><foodType>VegetarianRoughAnimal, DendrovoreAnimal</foodType>
+        </race></ThingDef></Defs>"#).race.unwrap();
+        assert_eq!(animal.kind, PawnKind::Animal);
+        assert_eq!(animal.intelligence, None);
+        assert_eq!(animal.food_type, ["VegetarianRoughAnimal", "DendrovoreAnimal"]);
+        assert_eq!(animal.body.as_deref(), Some("QuadrupedAnimalWithHooves"));
+
+        let human = structured(r#"<Defs><ThingDef><defName>Human</defName><race>
+            <intelligence>Humanlike</intelligence><body>Human</body><foodType>OmnivoreHuman</foodType>
+        </race></ThingDef></Defs>"#);
+        let race = human.race.as_ref().unwrap();
+        assert_eq!((race.kind, race.intelligence.as_deref()), (PawnKind::Humanlike, Some("Humanlike")));
+        assert!(human.references().contains(&"Human".to_string()));
+
+        let mech = structured(r#"<Defs><ThingDef><defName>Mech_Scyther</defName><race>
+            <intelligence>ToolUser</intelligence><fleshType>Mechanoid</fleshType>
+        </race></ThingDef></Defs>"#).race.unwrap();
+        assert_eq!(mech.kind, PawnKind::Mechanoid);
+
+        assert_eq!(pawn_kind(Some("ToolUser"), None), PawnKind::Other);
+        assert!(structured("<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>").race.is_none());
+    }
+
+    #[test]
+    fn growth_from_life_stage_ages() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Muffalo</defName>
+            <statBases><Wildness>0.6</Wildness></statBases>
+            <race>
+                <baseBodySize>2.4</baseBodySize>
+                <gestationPeriodDays>10</gestationPeriodDays>
+                <lifeStageAges>
+                    <li><def>AnimalBaby</def><minAge>0</minAge></li>
+                    <li><def>AnimalAdult</def><minAge>0.5</minAge></li>
+                </lifeStageAges>
+            </race>
+        </ThingDef></Defs>"#);
+
+        let growth = fields.growth.as_ref().unwrap();
+        let stages: Vec<(&str, Option<f32>)> = growth.life_stages.iter().map(|stage| (stage.def.as_str(), stage.min_age)).collect();
+        assert_eq!(stages, [("AnimalBaby", Some(0.0)), ("AnimalAdult", Some(0.5))]);
+        assert_eq!((growth.body_size, growth.wildness, growth.gestation_days), (Some(2.4), Some(0.6), Some(10.0)));
+        let references = fields.references();
+        assert!(references.contains(&"AnimalBaby".to_string()) && references.contains(&"AnimalAdult".to_string()));
+    }
+
+    #[test]
+    fn recipe_maker_with_skill_requirement_and_workbench() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Gun_Revolver</defName>
+            <statBases><WorkToMake>12000</WorkToMake></statBases>
+            <recipeMaker>
+                <workSkill>Crafting</workSkill>
+                <skillRequirements><Crafting>3</Crafting></skillRequirements>
+                <recipeUsers><li>TableMachining</li></recipeUsers>
+                <researchPrerequisite>Gunsmithing</researchPrerequisite>
+            </recipeMaker>
+        </ThingDef></Defs>"#);
+
+        let recipe_maker = fields.recipe_maker.as_ref().unwrap();
+        assert_eq!(recipe_maker.work_amount, Some(12000.0));
+        assert_eq!(recipe_maker.work_skill.as_deref(), Some("Crafting"));
+        let requirements: Vec<(&str, Option<u32>)> = recipe_maker.skill_requirements.iter().map(|req| (req.skill.as_str(), req.min_level)).collect();
+        assert_eq!(requirements, [("Crafting", Some(3))]);
+        assert_eq!(recipe_maker.recipe_users, ["TableMachining"]);
+        assert_eq!(recipe_maker.research_prerequisites, ["Gunsmithing"]);
+        assert!(fields.references().contains(&"TableMachining".to_string()));
+    }
+
+    #[test]
+    fn melee_tools_with_capacities() {
+        let fields = structured(r#"<Defs><ThingDef><defName>MeleeWeapon_Knife</defName>
+            <tools>
+                <li>
+                    <label>point</label>
+                    <capacities><li>Stab</li></capacities>
+                    <power>13</power>
+                    <cooldownTime>1.6</cooldownTime>
+                </li>
+                <li>
+                    <label>handle</label>
+                    <capacities><li>Blunt</li></capacities>
+                    <power>9</power>
+                    <cooldownTime>2</cooldownTime>
+                    <linkedBodyPartsGroup>Handle</linkedBodyPartsGroup>
+                </li>
+            </tools>
+        </ThingDef></Defs>"#);
+
+        let tools = fields.tools.as_ref().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!((tools[0].label.as_deref(), tools[0].power, tools[0].cooldown), (Some("point"), Some(13.0), Some(1.6)));
+        assert_eq!(tools[0].capacities, ["Stab"]);
+        assert_eq!(tools[1].capacities, ["Blunt"]);
+        assert_eq!(tools[1].linked_body_parts_group.as_deref(), Some("Handle"));
+        let references = fields.references();
+        assert!(["Stab", "Blunt", "Handle"].iter().all(|name| references.contains(&name.to_string())));
+    }
+
+    #[test]
+    fn graphic_classes_read_tex_path_differently() {
+        let multi = structured(r#"<Defs><ThingDef><defName>Muffalo</defName><graphicData>
+            <texPath>Things/Pawn/Animal/Muffalo/Muffalo</texPath>
+            <graphicClass>Graphic_Multi</graphicClass>
+            <drawSize>(2.5,2.5)</drawSize>
+        </graphicData></ThingDef></Defs>"#).graphic.unwrap();
+        assert_eq!(multi.graphic_class.as_deref(), Some("Graphic_Multi"));
+        assert_eq!(multi.tex_path.as_deref(), Some("Things/Pawn/Animal/Muffalo/Muffalo"));
+        assert_eq!(multi.tex_path_kind, TexPathKind::Directional);
+        assert_eq!(multi.draw_size, Some(vec![2.5, 2.5]));
+        assert!(!multi.atlas);
+
+        let single = structured(r#"<Defs><ThingDef><defName>Wall</defName><graphicData>
+            <texPath>Things/Building/Linked/Wall</texPath>
+            <graphicClass>Graphic_Single</graphicClass>
+            <linkType>CornerFiller</linkType>
+        </graphicData></ThingDef></Defs>"#).graphic.unwrap();
+        assert_eq!(single.graphic_class.as_deref(), Some("Graphic_Single"));
+        assert_eq!(single.tex_path.as_deref(), Some("Things/Building/Linked/Wall"));
+        assert_eq!(single.tex_path_kind, TexPathKind::File);
+        assert!(single.atlas);
+
+        assert_eq!(tex_path_kind(Some("Graphic_StackCount")), TexPathKind::Folder);
+        assert_eq!(tex_path_kind(Some("Graphic_Custom")), TexPathKind::Unknown);
+    }
+
+    #[test]
+    fn turret_building_references_its_gun() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Turret_MiniTurret</defName><building>
+            <turretGunDef>Gun_MiniTurret</turretGunDef>
+            <turretBurstCooldownTime>4.8</turretBurstCooldownTime>
+            <ai_combatDangerous>true</ai_combatDangerous>
+            <buildingTags><li>Production</li></buildingTags>
+        </building></ThingDef></Defs>"#);
+
+        let building = fields.building.as_ref().unwrap();
+        assert_eq!(building["turretGunDef"], serde_json::json!("Gun_MiniTurret"));
+        assert_eq!(building["turretBurstCooldownTime"], serde_json::json!(4.8));
+        assert_eq!(building["ai_combatDangerous"], serde_json::json!(true));
+        assert_eq!(building["buildingTags"], serde_json::json!(["Production"]));
+        let references = fields.references();
+        assert!(references.contains(&"Gun_MiniTurret".to_string()));
+        assert!(!references.contains(&"Production".to_string()));
+    }
+
+    #[test]
+    fn granted_abilities_and_ability_defs() {
+        let gene = structured(r#"<Defs><GeneDef><defName>FireSpew</defName>
+            <abilities><li>FireSpew</li><li>FoamSpray</li></abilities>
+        </GeneDef></Defs>"#);
+        assert_eq!(gene.abilities.as_deref(), Some(&["FireSpew".to_string(), "FoamSpray".to_string()][..]));
+        let references = gene.references();
+        assert!(references.contains(&"FireSpew".to_string()) && references.contains(&"FoamSpray".to_string()));
+
+        let ability = structured(r#"<Defs><AbilityDef><defName>Stun</defName>
+            <cooldownTicksRange>60000~120000</cooldownTicksRange>
+            <verbProperties>
+                <range>24.9</range>
+                <targetParams><canTargetPawns>True</canTargetPawns><canTargetBuildings>false</canTargetBuildings></targetParams>
+            </verbProperties>
+            <comps><li Class="CompProperties_AbilityGiveHediff"><hediffDef>PsychicStun</hediffDef></li></comps>
+        </AbilityDef></Defs>"#);
+        let details = ability.ability.as_ref().unwrap();
+        assert_eq!(details.cooldown_ticks.as_deref(), Some("60000~120000"));
+        assert_eq!(details.range, Some(24.9));
+        assert_eq!(details.targets, ["canTargetPawns"]);
+        assert_eq!(details.hediffs, ["PsychicStun"]);
+        assert!(ability.references().contains(&"PsychicStun".to_string()));
+        assert!(gene.ability.is_none());
+    }
+
+    #[test]
+    fn drug_hediffs_applied() {
+        let fields = structured(r#"<Defs><ThingDef><defName>GoJuice</defName>
+            <ingestible><outcomeDoers>
+                <li Class="IngestionOutcomeDoer_GiveHediff"><hediffDef>GoJuiceHigh</hediffDef></li>
+            </outcomeDoers></ingestible>
+            <comps><li Class="CompProperties_Drug"><chemical>GoJuice</chemical></li></comps>
+        </ThingDef></Defs>"#);
+
+        let applied: Vec<(&str, &str)> = fields.hediffs_applied.iter().flatten()
+            .map(|applied| (applied.hediff.as_str(), applied.via.as_str()))
+            .collect();
+        assert_eq!(applied, [("GoJuiceHigh", "ingestible")]);
+        assert!(fields.references().contains(&"GoJuiceHigh".to_string()));
+
+        let giver = structured(r#"<Defs><HediffGiverSetDef><defName>OrganicStandard</defName>
+            <hediffGivers><li Class="HediffGiver_Birthday"><hediff>BadBack</hediff></li></hediffGivers>
+        </HediffGiverSetDef></Defs>"#);
+        assert_eq!(giver.hediffs_applied.unwrap()[0].via, "hediffGivers");
+    }
+
+    #[test]
+    fn weapon_sounds() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Gun_Revolver</defName>
+            <soundInteract>Interact_Revolver</soundInteract>
+            <verbs>
+                <li><soundCast>Shot_Revolver</soundCast><soundCastTail>GunTail_Light</soundCastTail></li>
+                <li><soundCast>Shot_Other</soundCast></li>
+            </verbs>
+            <projectile><soundImpact>BulletImpact_Metal</soundImpact><soundAmbientVolume>0.5</soundAmbientVolume></projectile>
+        </ThingDef></Defs>"#);
+
+        let sounds = fields.sounds.as_ref().unwrap();
+        assert_eq!(sounds.get("soundImpact").map(String::as_str), Some("BulletImpact_Metal"));
+        assert_eq!(sounds.get("soundInteract").map(String::as_str), Some("Interact_Revolver"));
+        assert_eq!(sounds.get("soundCast").map(String::as_str), Some("Shot_Revolver"));
+        assert!(!sounds.contains_key("soundAmbientVolume"));
+        assert!(fields.references().contains(&"BulletImpact_Metal".to_string()));
+    }
+
+    #[test]
+    fn crop_plant_harvest() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Plant_Rice</defName><plant>
+            <growDays>3</growDays>
+            <harvestedThingDef>RawRice</harvestedThingDef>
+            <harvestYield>6</harvestYield>
+            <fertilityMin>0.7</fertilityMin>
+            <sowTags><li>Ground</li><li>Hydroponic</li></sowTags>
+        </plant></ThingDef></Defs>"#);
+
+        let plant = fields.plant.as_ref().unwrap();
+        assert_eq!(plant.grow_days, Some(3.0));
+        assert_eq!(plant.harvested_thing.as_deref(), Some("RawRice"));
+        assert_eq!((plant.harvest_yield, plant.fertility_min), (Some(6.0), Some(0.7)));
+        assert_eq!(plant.sow_tags, ["Ground", "Hydroponic"]);
+        assert!(fields.references().contains(&"RawRice".to_string()));
+
+        let decorative = structured(r#"<Defs><ThingDef><defName>Plant_Rose</defName><plant><growDays>5</growDays></plant></ThingDef></Defs>"#);
+        assert_eq!(decorative.plant.unwrap().harvested_thing, None);
+    }
+
+    #[test]
+    fn meme_precept_and_ritual_links() {
+        let meme = structured(r#"<Defs><MemeDef><defName>Cannibal</defName><requireOne>
+            <li><li>Cannibalism_Preferred</li></li>
+            <li><li>Cannibalism_RequiredStrong</li></li>
+        </requireOne></MemeDef></Defs>"#);
+        let ideo = meme.ideo.as_ref().unwrap();
+        assert_eq!(ideo.precepts, ["Cannibalism_Preferred", "Cannibalism_RequiredStrong"]);
+        let references = meme.references();
+        assert!(references.contains(&"Cannibalism_Preferred".to_string()) && references.contains(&"Cannibalism_RequiredStrong".to_string()));
+
+        let precept = structured(r#"<Defs><PreceptDef><defName>Funeral</defName>
+            <associatedMemes><li>Loyalist</li></associatedMemes>
+            <ritualPatternBase>Funeral</ritualPatternBase>
+        </PreceptDef></Defs>"#).ideo.unwrap();
+        assert_eq!((precept.memes, precept.ritual_patterns), (vec!["Loyalist".to_string()], vec!["Funeral".to_string()]));
+
+        let pattern = structured(r#"<Defs><RitualPatternDef><defName>Funeral</defName>
+            <ritualOutcomeEffect>Funeral</ritualOutcomeEffect><ritualBehavior>Funeral</ritualBehavior>
+        </RitualPatternDef></Defs>"#).ideo.unwrap();
+        assert_eq!((pattern.ritual_outcome.as_deref(), pattern.ritual_behavior.as_deref()), (Some("Funeral"), Some("Funeral")));
+
+        assert!(structured("<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>").ideo.is_none());
+    }
+
+    #[test]
+    fn stat_offsets_and_factors() {
+        let apparel = structured(r#"<Defs><ThingDef><defName>Apparel_PsychicFoilHelmet</defName>
+            <equippedStatOffsets><PsychicSensitivity>-0.8</PsychicSensitivity></equippedStatOffsets>
+        </ThingDef></Defs>"#);
+        let modifiers = apparel.stat_modifiers.as_ref().unwrap();
+        assert_eq!(modifiers.equipped_stat_offsets.get("PsychicSensitivity"), Some(&-0.8));
+        assert!(modifiers.stat_offsets.is_empty() && modifiers.stat_factors.is_empty());
+        assert!(apparel.references().contains(&"PsychicSensitivity".to_string()));
+
+        let hediff = structured(r#"<Defs><HediffDef><defName>Bionic</defName>
+            <statOffsets><MoveSpeed>0.5</MoveSpeed></statOffsets>
+            <statFactors><PainShockThreshold>1.2</PainShockThreshold></statFactors>
+        </HediffDef></Defs>"#).stat_modifiers.unwrap();
+        assert_eq!(hediff.stat_offsets.get("MoveSpeed"), Some(&0.5));
+        assert_eq!(hediff.stat_factors.get("PainShockThreshold"), Some(&1.2));
+        assert!(hediff.equipped_stat_offsets.is_empty());
+    }
+
+    #[test]
+    fn recipe_skill_requirements() {
+        let fields = structured(r#"<Defs><RecipeDef><defName>Make_ComponentIndustrial</defName>
+            <skillRequirements><Crafting>6</Crafting></skillRequirements>
+        </RecipeDef></Defs>"#);
+        let requirements: Vec<(&str, Option<u32>)> = fields.skill_requirements.iter().flatten()
+            .map(|req| (req.skill.as_str(), req.min_level))
+            .collect();
+        assert_eq!(requirements, [("Crafting", Some(6))]);
+        assert!(fields.references().contains(&"Crafting".to_string()));
+
+        let listed = structured(r#"<Defs><RecipeDef><defName>Surgery</defName>
+            <skillRequirements><li><skill>Medicine</skill><minLevel>8</minLevel></li></skillRequirements>
+        </RecipeDef></Defs>"#).skill_requirements.unwrap();
+        assert_eq!((listed[0].skill.as_str(), listed[0].min_level), ("Medicine", Some(8)));
+    }
+
+    #[test]
+    fn butcher_products_and_killed_leavings() {
+        let animal = structured(r#"<Defs><ThingDef><defName>Muffalo</defName>
+            <butcherProducts><Meat_Muffalo>140</Meat_Muffalo><Leather_Bluefur>50</Leather_Bluefur></butcherProducts>
+        </ThingDef></Defs>"#);
+        let drops = animal.drops.as_ref().unwrap();
+        assert_eq!(drops.butcher_products.iter().map(|(name, count)| (name.as_str(), *count)).collect::<Vec<_>>(),
+            [("Leather_Bluefur", 50), ("Meat_Muffalo", 140)]);
+        assert!(drops.killed_leavings.is_empty() && !drops.leaves_resources_when_killed);
+        let references = animal.references();
+        assert!(references.contains(&"Meat_Muffalo".to_string()) && references.contains(&"Leather_Bluefur".to_string()));
+
+        let building = structured(r#"<Defs><ThingDef><defName>Wall</defName>
+            <killedLeavings><ChunkSlagSteel>1</ChunkSlagSteel></killedLeavings>
+            <leaveResourcesWhenKilled>true</leaveResourcesWhenKilled>
+        </ThingDef></Defs>"#).drops.unwrap();
+        assert_eq!(building.killed_leavings.get("ChunkSlagSteel"), Some(&1));
+        assert!(building.leaves_resources_when_killed);
+    }
+
+    #[test]
+    fn thing_set_maker_tags() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Gun_ChargeRifle</defName>
+            <thingSetMakerTags><li>RewardStandardHighFreq</li><li>SingleUseWeapon</li></thingSetMakerTags>
+        </ThingDef></Defs>"#);
+        assert_eq!(fields.thing_set_maker_tags.as_deref(), Some(&["RewardStandardHighFreq".to_string(), "SingleUseWeapon".to_string()][..]));
+
+        let empty = structured("<Defs><ThingDef><defName>Steel</defName><thingSetMakerTags/></ThingDef></Defs>");
+        assert!(empty.thing_set_maker_tags.is_none());
+    }
+
+    #[test]
+    fn trade_field() {
+        let fields = structured(r#"<Defs><ThingDef><defName>Gold</defName>
+            <statBases><MarketValue>10</MarketValue></statBases>
+            <tradeTags><li>ExoticMisc</li><li>ResourcesRaw</li></tradeTags>
+        </ThingDef></Defs>"#);
+        let trade = fields.trade.as_ref().unwrap();
+        assert_eq!(trade.tradeability, "All");
+        assert_eq!(trade.trade_tags, ["ExoticMisc", "ResourcesRaw"]);
+        assert_eq!(trade.market_value, Some(10.0));
+        assert!(!trade.is_currency);
+
+        let silver = structured(r#"<Defs><ThingDef><defName>Silver</defName><tradeability>Sellable</tradeability></ThingDef></Defs>"#).trade.unwrap();
+        assert_eq!(silver.tradeability, "Sellable");
+        assert!(silver.is_currency);
+
+        assert!(structured("<Defs><ThingDef><defName>Filth</defName></ThingDef></Defs>").trade.is_none());
+        assert!(structured("<Defs><HediffDef><defName>Flu</defName><tradeTags><li>X</li></tradeTags></HediffDef></Defs>").trade.is_none());
+    }
+
+    #[test]
+    fn apparel_coverage_and_body_parts() {
+        let apparel = structured(r#"<Defs><ThingDef><defName>Apparel_FlakVest</defName><apparel>
+            <bodyPartGroups><li>Torso</li><li>Shoulders</li></bodyPartGroups>
+            <layers><li>Middle</li></layers>
+        </apparel></ThingDef></Defs>"#);
+        let coverage = apparel.coverage.as_ref().unwrap();
+        assert_eq!(coverage.body_part_groups, ["Torso", "Shoulders"]);
+        assert_eq!(coverage.layers, ["Middle"]);
+        let references = apparel.references();
+        assert!(["Torso", "Shoulders", "Middle"].iter().all(|name| references.contains(&name.to_string())));
+
+        let body = structured(r#"<Defs><BodyDef><defName>Human</defName><corePart>
+            <def>Torso</def><groups><li>Torso</li></groups>
+            <parts><li><def>Arm</def><customLabel>left arm</customLabel><coverage>0.12</coverage></li></parts>
+        </corePart></BodyDef></Defs>"#);
+        let torso = body.body_parts.as_ref().unwrap();
+        assert_eq!((torso.def.as_str(), &torso.groups[..]), ("Torso", &["Torso".to_string()][..]));
+        assert_eq!(torso.parts[0].custom_label.as_deref(), Some("left arm"));
+        assert_eq!(torso.parts[0].coverage, Some(0.12));
+        assert!(body.references().contains(&"Arm".to_string()));
+    }
+}
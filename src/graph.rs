@@ -0,0 +1,218 @@
+//! Projection of the defs and their `references_out` onto a node/edge graph for export,
+//! as JSON (`--emit-graph`) or GraphViz DOT (`--format dot`). Nodes are keyed by defName
+//! since references are by name; when several defs share a name the first one describes
+//! the node.
+
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::RimWorldDef;
+
+pub struct Graph<'a> {
+    pub nodes: Vec<&'a RimWorldDef>,
+    pub edges: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Graph<'a> {
+    /// Every def as a node and every reference between two of them as an edge. With
+    /// `def_type`, only defs of that type and the edges among them are kept.
+    pub fn project(defs: &'a [RimWorldDef], def_type: Option<&str>) -> Self {
+        let mut by_name: HashMap<&str, &RimWorldDef> = HashMap::new();
+        let mut nodes = Vec::new();
+        for def in defs {
+            if def_type.is_some_and(|def_type| def.def_type != def_type) {
+                continue;
+            }
+            if !by_name.contains_key(def.def_name.as_str()) {
+                by_name.insert(&def.def_name, def);
+                nodes.push(def);
+            }
+        }
+
+        let mut edges = BTreeSet::new();
+        for def in defs {
+            if !by_name.contains_key(def.def_name.as_str()) || def_type.is_some_and(|def_type| def.def_type != def_type) {
+                continue;
+            }
+            for target in &def.references_out {
+                if by_name.contains_key(target.as_str()) {
+                    edges.insert((def.def_name.as_str(), target.as_str()));
+                }
+            }
+        }
+
+        Self {
+            nodes,
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// The part of the graph within `depth` hops of `root`, following edges in both
+    /// directions so a def's dependents are included along with its dependencies.
+    /// None when no node is named `root`.
+    pub fn neighborhood(&self, root: &str, depth: usize) -> Option<Self> {
+        let root = self.nodes.iter().find(|def| def.def_name == root)?.def_name.as_str();
+
+        let mut reached: HashSet<&str> = HashSet::from([root]);
+        let mut queue = VecDeque::from([(root, 0)]);
+        while let Some((name, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+            let neighbours = self.edges.iter().filter_map(|&(source, target)| {
+                if source == name {
+                    Some(target)
+                } else if target == name {
+                    Some(source)
+                } else {
+                    None
+                }
+            });
+            for neighbour in neighbours {
+                if reached.insert(neighbour) {
+                    queue.push_back((neighbour, distance + 1));
+                }
+            }
+        }
+
+        Some(Self {
+            nodes: self.nodes.iter().copied().filter(|def| reached.contains(def.def_name.as_str())).collect(),
+            edges: self.edges.iter().copied()
+                .filter(|(source, target)| reached.contains(source) && reached.contains(target))
+                .collect(),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "nodes": self.nodes.iter().map(|def| json!({
+                "id": def.def_name,
+                "def_type": def.def_type,
+                "label": def.label,
+                "extension": def.extension
+            })).collect::<Vec<_>>(),
+            "edges": self.edges.iter().map(|(source, target)| json!({
+                "source": source,
+                "target": target
+            })).collect::<Vec<_>>()
+        })
+    }
+
+    /// GraphViz DOT with nodes labeled by defName and type, filled by extension.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph defs {\n");
+        dot.push_str("  node [shape=box, style=filled, fontname=\"Helvetica\"];\n");
+
+        for def in &self.nodes {
+            dot.push_str(&format!("  {} [label={}, fillcolor=\"{}\"];\n",
+                dot_id(&def.def_name),
+                dot_id(&format!("{}\n{}", def.def_name, def.def_type)),
+                extension_color(&def.extension)));
+        }
+        for (source, target) in &self.edges {
+            dot.push_str(&format!("  {} -> {};\n", dot_id(source), dot_id(target)));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A quoted DOT identifier; `\n` in the text stays a DOT line break.
+fn dot_id(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn extension_color(extension: &str) -> &'static str {
+    match extension {
+        "Core" => "#e8e8e8",
+        "Royalty" => "#f4e3a1",
+        "Ideology" => "#c9e4c5",
+        "Biotech" => "#b9d7f0",
+        "Anomaly" => "#e2c4e8",
+        "Odyssey" => "#f6c9a8",
+        _ => "#ffffff",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+    use std::path::Path;
+
+    /// Defs from `(def_type, def_name, references_out)`, all in Core.
+    fn defs(specs: &[(&str, &str, &[&str])]) -> Vec<RimWorldDef> {
+        specs.iter().map(|(def_type, def_name, references_out)| {
+            let xml = format!("<Defs><{0}><defName>placeholder</defName></{0}></Defs>", def_type);
+            let mut def = DefParser::new(String::new()).parse_xml_str(&xml, Path::new("Data/Core/Defs/Test.xml")).unwrap().remove(0);
+            def.def_name = def_name.to_string();
+            def.extension = "Core".to_string();
+            def.references_out = references_out.iter().map(|name| name.to_string()).collect();
+            def
+        }).collect()
+    }
+
+    #[test]
+    fn dot_output_escapes_quotes_in_ids() {
+        let defs = defs(&[
+            ("ThingDef", "Steel", &[]),
+            ("RecipeDef", "Make_\"Steel\"", &["Steel"]),
+        ]);
+        let graph = Graph::project(&defs, None);
+
+        assert_eq!(graph.to_dot(), concat!(
+            "digraph defs {\n",
+            "  node [shape=box, style=filled, fontname=\"Helvetica\"];\n",
+            "  \"Steel\" [label=\"Steel\\nThingDef\", fillcolor=\"#e8e8e8\"];\n",
+            "  \"Make_\\\"Steel\\\"\" [label=\"Make_\\\"Steel\\\"\\nRecipeDef\", fillcolor=\"#e8e8e8\"];\n",
+            "  \"Make_\\\"Steel\\\"\" -> \"Steel\";\n",
+            "}\n",
+        ));
+        assert_eq!(dot_id("a\\b"), "\"a\\\\b\"");
+    }
+
+    fn names<'a>(graph: &Graph<'a>) -> Vec<&'a str> {
+        let mut names: Vec<&str> = graph.nodes.iter().map(|def| def.def_name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn projection_keeps_only_the_given_def_type() {
+        let defs = defs(&[
+            ("ThingDef", "Gun_Revolver", &["Steel", "Make_Revolver"]),
+            ("ThingDef", "Steel", &[]),
+            ("RecipeDef", "Make_Revolver", &["Gun_Revolver", "Steel"]),
+        ]);
+
+        let all = Graph::project(&defs, None);
+        assert_eq!(names(&all), ["Gun_Revolver", "Make_Revolver", "Steel"]);
+        assert_eq!(all.edges.len(), 4);
+
+        let things = Graph::project(&defs, Some("ThingDef"));
+        assert_eq!(names(&things), ["Gun_Revolver", "Steel"]);
+        assert_eq!(things.edges, [("Gun_Revolver", "Steel")]);
+    }
+
+    #[test]
+    fn neighborhood_stops_at_the_depth_limit() {
+        // A -> B -> C -> D, and E -> B as a dependent of B
+        let defs = defs(&[
+            ("ThingDef", "A", &["B"]),
+            ("ThingDef", "B", &["C"]),
+            ("ThingDef", "C", &["D"]),
+            ("ThingDef", "D", &[]),
+            ("ThingDef", "E", &["B"]),
+        ]);
+        let graph = Graph::project(&defs, None);
+
+        assert_eq!(names(&graph.neighborhood("A", 0).unwrap()), ["A"]);
+        assert_eq!(names(&graph.neighborhood("A", 1).unwrap()), ["A", "B"]);
+        let two = graph.neighborhood("A", 2).unwrap();
+        assert_eq!(names(&two), ["A", "B", "C", "E"]);
+        assert_eq!(two.edges, [("A", "B"), ("B", "C"), ("E", "B")]);
+        assert_eq!(names(&graph.neighborhood("C", 1).unwrap()), ["B", "C", "D"]);
+        assert!(graph.neighborhood("Missing", 3).is_none());
+    }
+}
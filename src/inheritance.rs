@@ -0,0 +1,119 @@
+//! Merging of `ParentName` inheritance, following the game's XmlInheritance rules:
+//! child elements replace parent elements of the same name, list entries (`li`) are
+//! appended, and nested elements are merged recursively. `Inherit="False"` on a child
+//! element drops the parent's element of that name instead of merging with it.
+
+use crate::DefElement;
+
+/// Parent elements with `child` laid over them.
+pub fn merge_elements(parent: &[DefElement], child: &[DefElement]) -> Vec<DefElement> {
+    let mut merged = parent.to_vec();
+
+    for element in child {
+        match merged.iter_mut().find(|m| m.name == element.name && m.name != "li") {
+            Some(existing) => merge_into(existing, element),
+            None => merged.push(without_inherit(element)),
+        }
+    }
+
+    merged
+}
+
+fn merge_into(existing: &mut DefElement, element: &DefElement) {
+    if element.attr("Inherit").is_some_and(|v| v.eq_ignore_ascii_case("false")) || element.children.is_empty() {
+        // Plain values and Inherit="False" replace the inherited element
+        *existing = without_inherit(element);
+        return;
+    }
+
+    existing.attributes.extend(element.attributes.iter()
+        .filter(|(k, _)| *k != "Inherit")
+        .map(|(k, v)| (k.clone(), v.clone())));
+
+    if element.children.iter().all(|c| c.name == "li") {
+        existing.children.extend(element.children.iter().map(without_inherit));
+    } else {
+        existing.children = merge_elements(&existing.children, &element.children);
+    }
+}
+
+/// A copy of the element without `Inherit` attributes anywhere in it. The attribute only
+/// steers inheritance, the game doesn't keep it.
+fn without_inherit(element: &DefElement) -> DefElement {
+    let mut element = element.clone();
+    let mut pending = vec![&mut element];
+    while let Some(current) = pending.pop() {
+        current.attributes.shift_remove("Inherit");
+        pending.extend(current.children.iter_mut());
+    }
+    element
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+    use std::path::Path;
+
+    /// The elements of each def in the Defs document, in order.
+    fn elements(xml: &str) -> Vec<Vec<DefElement>> {
+        DefParser::new(String::new()).parse_xml_str(xml, Path::new("Items.xml")).unwrap()
+            .into_iter()
+            .map(|def| def.elements)
+            .collect()
+    }
+
+    fn content<'a>(elements: &'a [DefElement], path: &str) -> Option<&'a str> {
+        let (first, rest) = path.split_once('.').unwrap_or((path, ""));
+        let element = elements.iter().find(|e| e.name == first)?;
+        if rest.is_empty() { element.content.as_deref() } else { element.get_content(rest) }
+    }
+
+    #[test]
+    fn two_level_chain_overrides_one_stat() {
+        let defs = elements(r#"<Defs>
+            <ThingDef><statBases><MarketValue>1</MarketValue><Mass>0.5</Mass></statBases><stackLimit>75</stackLimit></ThingDef>
+            <ThingDef><statBases><Mass>0.8</Mass></statBases></ThingDef>
+            <ThingDef><defName>Steel</defName><statBases><MarketValue>1.9</MarketValue></statBases></ThingDef>
+        </Defs>"#);
+
+        let parent = merge_elements(&defs[0], &defs[1]);
+        let child = merge_elements(&parent, &defs[2]);
+        assert_eq!(content(&child, "statBases.MarketValue"), Some("1.9"));
+        assert_eq!(content(&child, "statBases.Mass"), Some("0.8"));
+        assert_eq!(content(&child, "stackLimit"), Some("75"));
+        assert_eq!(content(&child, "defName"), Some("Steel"));
+    }
+
+    #[test]
+    fn lists_are_appended_unless_inherit_is_false() {
+        let defs = elements(r#"<Defs>
+            <ThingDef><comps><li>A</li><li>B</li></comps><tradeTags><li>Raw</li></tradeTags></ThingDef>
+            <ThingDef><comps><li>C</li></comps><tradeTags Inherit="False"><li>Exotic</li></tradeTags></ThingDef>
+        </Defs>"#);
+
+        let merged = merge_elements(&defs[0], &defs[1]);
+        let items = |name: &str| -> Vec<String> {
+            merged.iter().find(|e| e.name == name).unwrap().children.iter()
+                .filter_map(|li| li.content.clone())
+                .collect()
+        };
+        assert_eq!(items("comps"), ["A", "B", "C"]);
+        assert_eq!(items("tradeTags"), ["Exotic"]);
+        assert_eq!(merged.iter().find(|e| e.name == "tradeTags").unwrap().attr("Inherit"), None);
+    }
+
+    #[test]
+    fn plain_values_replace_inherited_ones() {
+        let defs = elements(r#"<Defs>
+            <ThingDef><label>base</label><graphicData><texPath>Base</texPath><drawSize>2</drawSize></graphicData></ThingDef>
+            <ThingDef><label>child</label><graphicData><texPath>Child</texPath></graphicData></ThingDef>
+        </Defs>"#);
+
+        let merged = merge_elements(&defs[0], &defs[1]);
+        assert_eq!(content(&merged, "label"), Some("child"));
+        assert_eq!(content(&merged, "graphicData.texPath"), Some("Child"));
+        assert_eq!(content(&merged, "graphicData.drawSize"), Some("2"));
+        assert_eq!(merged.iter().filter(|e| e.name == "label").count(), 1);
+    }
+}
@@ -0,0 +1,2387 @@
+//! Parsing of RimWorld's XML defs: every def of an install (and of extra mod folders)
+//! as a [`RimWorldDef`] with its element tree, references in both directions and, on
+//! request, the elements inherited through `ParentName`. The `rimworld-def-viewer` binary
+//! builds its datasets on top of this.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! let defs = rimworld_def_viewer::parse_defs(Path::new("/games/RimWorld"))?;
+//! for def in defs.iter().filter(|def| def.def_type == "ThingDef") {
+//!     println!("{}: {:?}, referenced by {:?}", def.def_name, def.label, def.references_in);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [`DefParser`] exposes the settings behind the CLI flags (MayRequire filtering, reference
+//! mode, extension rules, ...) and the checks run after scanning.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesEnd, BytesStart, Event};
+use quick_xml::Reader;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+mod assets;
+mod cache;
+pub mod deprecated;
+pub mod extension_rules;
+pub mod extract;
+mod inheritance;
+mod lint;
+mod mods;
+
+use cache::ParseCache;
+use deprecated::DeprecationTable;
+use extension_rules::ExtensionRules;
+use extract::StructuredFields;
+use mods::ModInfo;
+
+/// Default for the deepest element nesting accepted in a def (`--recursion-limit`). Real
+/// defs stay below 20 levels; anything deeper is rejected per file so the recursive tree
+/// walks (`to_xml`, inheritance merging, serialization, dropping the tree) can't overflow
+/// the stack.
+pub const DEFAULT_RECURSION_LIMIT: usize = 512;
+
+/// Elements holding player-facing text, whose content is never taken as a def reference
+/// even when it happens to match a defName.
+const FREE_TEXT_FIELDS: &[&str] = &[
+    "label",
+    "labelShort",
+    "labelPlural",
+    "labelNoun",
+    "labelMale",
+    "labelFemale",
+    "description",
+    "descriptionShort",
+    "jobString",
+    "gerundLabel",
+    "reportString",
+    "pawnLabel",
+    "letterLabel",
+    "letterText",
+    "baseDescription",
+    "text",
+];
+
+/// Elements whose children are named after defs, like `<costList><Steel>10</Steel></costList>`.
+/// Outside strict mode their children's names are references, not only in loose mode.
+const DEF_NAME_MAPS: &[&str] = &[
+    "costList",
+    "butcherProducts",
+    "killedLeavings",
+    "smeltProducts",
+    "statBases",
+    "equippedStatOffsets",
+    "statOffsets",
+    "statFactors",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefElement {
+    pub name: String,
+    pub attributes: IndexMap<String, String>,   // In source order
+    pub content: Option<String>,
+    pub children: Vec<DefElement>,
+    pub depth: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,       // Def a resolved element came from ("self" or an ancestor), see --field-provenance
+}
+
+impl DefElement {
+    /// First direct child with the given tag name.
+    pub fn find_child(&self, name: &str) -> Option<&DefElement> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Every descendant with the given tag name, in document order.
+    pub fn find_all(&self, name: &str) -> Vec<&DefElement> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.find_all(name));
+        }
+        found
+    }
+
+    /// Text content at a dotted path of child names, e.g. `statBases.MarketValue`.
+    pub fn get_content(&self, path: &str) -> Option<&str> {
+        let mut element = self;
+        for name in path.split('.') {
+            element = element.find_child(name)?;
+        }
+        element.content.as_deref()
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(|v| v.as_str())
+    }
+
+    /// Marks this element and everything below it as coming from `origin`.
+    pub fn set_origin(&mut self, origin: &str) {
+        let mut pending = vec![self];
+        while let Some(element) = pending.pop() {
+            element.origin = Some(origin.to_string());
+            pending.extend(element.children.iter_mut());
+        }
+    }
+
+    pub fn to_xml(&self, indent: usize) -> String {
+        let mut xml = String::new();
+        let indent_str = "  ".repeat(indent);
+        
+        // Opening tag with attributes
+        xml.push_str(&format!("{}<{}", indent_str, self.name));
+        
+        // Add attributes if any
+        if !self.attributes.is_empty() {
+            for (key, value) in &self.attributes {
+                xml.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+            }
+        }
+        
+        // Check if this is a self-closing tag (no content and no children)
+        if self.content.is_none() && self.children.is_empty() {
+            xml.push_str(" />\n");
+            return xml;
+        }
+        
+        xml.push('>');
+        
+        // Add content if it exists
+        if let Some(content) = &self.content {
+            if self.children.is_empty() {
+                // Simple content on same line
+                xml.push_str(&escape(content));
+            } else {
+                // Content with children - put content on new line
+                xml.push('\n');
+                xml.push_str(&format!("{}{}", "  ".repeat(indent + 1), escape(content)));
+                xml.push('\n');
+            }
+        } else if !self.children.is_empty() {
+            xml.push('\n');
+        }
+        
+        // Add children
+        for child in &self.children {
+            xml.push_str(&child.to_xml(indent + 1));
+        }
+        
+        // Closing tag
+        if !self.children.is_empty() || (self.content.is_some() && !self.children.is_empty()) {
+            xml.push_str(&format!("{}</{}>", indent_str, self.name));
+        } else {
+            xml.push_str(&format!("</{}>", self.name));
+        }
+        xml.push('\n');
+        
+        xml
+    }
+
+    /// RimWorld serializes dictionaries as lists of `<li><key>..</key><value>..</value></li>`.
+    /// Returns the entries in source order when every child follows that shape.
+    pub fn key_value_map(&self) -> Option<Vec<(String, Option<String>)>> {
+        if self.children.is_empty() {
+            return None;
+        }
+
+        let mut entries = Vec::new();
+        for li in &self.children {
+            if li.name != "li" {
+                return None;
+            }
+            let key = li.get_content("key")?.to_string();
+            let value = li.find_child("value")?;
+            entries.push((key, value.content.clone()));
+        }
+
+        Some(entries)
+    }
+
+    /// Indented outline of the parsed tree, used by the `debug-file` subcommand.
+    pub fn to_outline(&self, indent: usize) -> String {
+        let mut outline = format!("{}{}", "  ".repeat(indent), self.name);
+
+        for (key, value) in &self.attributes {
+            outline.push_str(&format!(" [{}=\"{}\"]", key, value));
+        }
+
+        if let Some(content) = &self.content {
+            outline.push_str(&format!(" = {:?}", content));
+        }
+
+        outline.push_str(&format!(" (depth {})\n", self.depth));
+
+        for child in &self.children {
+            outline.push_str(&child.to_outline(indent + 1));
+        }
+
+        outline
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RimWorldDef {
+    pub def_name: String,
+    pub def_type: String,
+    pub label: Option<String>,
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherit_name: Option<String>,  // Name attribute that ParentName refers to
+    pub parent_name: Option<String>,
+    pub is_abstract: bool,
+    pub elements: Vec<DefElement>,
+    pub file_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_modified: Option<String>, // RFC 3339 mtime of the source file
+    #[serde(default)]
+    pub source_line: usize,            // 1-based line of the def's opening tag
+    #[serde(default)]
+    pub sequence: usize,               // Position in file-then-document order, see --preserve-order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_context: Option<SourceContext>, // Neighbouring source lines, see --include-source-context
+    pub tags: Vec<String>,
+    pub stats: Option<DefStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references_out: Vec<String>,  // DefNames this def references
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<Reference>,   // references_out with the field each was found in
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references_in: Vec<String>,   // DefNames that reference this def
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub code_references: Vec<String>, // C# class names referenced (from Class attributes)
+    pub raw_xml: String,             // Original XML representation
+    pub extension: String,           // RimWorld extension/DLC: Core, Royalty, Ideology, Biotech, Anomaly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_mod: Option<String>,  // Package id from the nearest About/About.xml, or of the official content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mod_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_elements: Option<Vec<DefElement>>, // Elements merged with the ParentName chain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thing_set_makers: Vec<String>, // ThingSetMakerDefs (reward and loot sets) allowing one of its tags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub obtainable: Option<Vec<String>>, // ThingDefs only: ways to obtain it, empty for internal defs
+    #[serde(flatten)]
+    pub structured: StructuredFields, // Typed views of well-known sub-structures (race, recipes, ...)
+}
+
+/// Lines of the source file immediately surrounding a def, kept so that
+/// comments and sibling defs can be seen without opening the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceContext {
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+impl RimWorldDef {
+    /// Value of a field such as `statBases/MarketValue` (dots work too), read from the
+    /// inheritance-resolved tree when it was computed and the def's own elements otherwise.
+    pub fn effective_field(&self, path: &str) -> Option<&str> {
+        field_content(self.resolved_elements.as_ref().unwrap_or(&self.elements), path)
+    }
+
+    /// Whether an element with this tag name appears anywhere in the def's own element tree.
+    pub fn has_element(&self, name: &str) -> bool {
+        self.elements.iter().any(|e| e.name == name || !e.find_all(name).is_empty())
+    }
+}
+
+/// Content of the element at `path` (slashes or dots) among `elements`.
+fn field_content<'a>(elements: &'a [DefElement], path: &str) -> Option<&'a str> {
+    let path = path.replace('/', ".");
+    let (first, rest) = match path.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (path.as_str(), None),
+    };
+
+    let element = elements.iter().find(|e| e.name == first)?;
+    match rest {
+        Some(rest) => element.get_content(rest),
+        None => element.content.as_deref(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefStats {
+    pub element_count: usize,
+    pub max_depth: usize,
+    pub has_complex_structure: bool,
+}
+
+/// How eagerly element names and contents are accepted as references, see --reference-mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceMode {
+    Strict,   // Only fields known to hold def names (structured extraction)
+    Balanced, // Plus element contents that exactly match a defName
+    Loose,    // Plus element names and attribute values
+}
+
+/// Scans an install and holds its defs; configure the public fields before
+/// `scan_defs_directory`, then run the checks and `resolve_inheritance` as needed.
+pub struct DefParser {
+    rimworld_data_path: String,
+    pub parsed_defs: Vec<RimWorldDef>,
+    def_name_map: HashMap<String, Vec<usize>>,      // Map def names to their indices in parsed_defs
+    name_handle_map: HashMap<String, Vec<usize>>, // Name attributes (what ParentName refers to) to indices
+    pub verbose: bool,                              // Trace root detection and <Defs> transitions
+    pub warnings: Vec<String>,                      // Problems that don't stop parsing, see --warnings-log
+    pub source_context_lines: usize,                // Lines captured around each def, 0 disables
+    pub reference_mode: ReferenceMode,
+    pub files_without_defs: Vec<PathBuf>,           // Parsed fine but yielded no definitions
+    pub reparsed_files: Vec<PathBuf>,               // Parsed again because the cache had no current copy
+    pub extension_rules: ExtensionRules,            // Path rules for detect_extension, see --extension-rules
+    pub recursion_limit: usize,                     // Deepest element nesting accepted, bounds every tree walk
+    pub active_mods: Option<HashSet<String>>,       // Lowercase package ids; drops inactive MayRequire content
+    pub field_provenance: bool,                     // Record each resolved element's origin def
+    pub inheritance_cycles: Vec<Vec<String>>,       // ParentName cycles, each as the defNames along it
+    pub dangling_references: Vec<(String, String)>, // (referencing defName, undefined name)
+    pub quiet: bool,                                // No progress on stdout, for machine-readable output
+    pub load_order: Option<Vec<String>>,            // Lowercase package ids; later mods override same-named defs
+    mod_dirs: Vec<(PathBuf, Option<ModInfo>)>,      // Mod folders scanned besides Data, see --mod-dir
+    mod_info_cache: HashMap<PathBuf, Option<ModInfo>>, // Nearest About.xml per directory
+    parse_cache: Option<(PathBuf, ParseCache)>, // Where to save it and the defs of unchanged files, see --cache
+}
+
+impl DefParser {
+    pub fn new(rimworld_data_path: String) -> Self {
+        Self {
+            rimworld_data_path,
+            parsed_defs: Vec::new(),
+            def_name_map: HashMap::new(),
+            name_handle_map: HashMap::new(),
+            verbose: false,
+            warnings: Vec::new(),
+            source_context_lines: 0,
+            reference_mode: ReferenceMode::Loose,
+            files_without_defs: Vec::new(),
+            reparsed_files: Vec::new(),
+            extension_rules: ExtensionRules::load(None).expect("built-in extension rules are valid"),
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+            active_mods: None,
+            field_provenance: false,
+            inheritance_cycles: Vec::new(),
+            dangling_references: Vec::new(),
+            quiet: false,
+            load_order: None,
+            mod_dirs: Vec::new(),
+            mod_info_cache: HashMap::new(),
+            parse_cache: None,
+        }
+    }
+
+    /// Loads the parse cache at `path`. Call after every setting that changes parse
+    /// results, which are part of the cache key.
+    pub fn enable_cache(&mut self, path: &Path) {
+        let mut active_mods: Vec<&String> = self.active_mods.iter().flatten().collect();
+        active_mods.sort();
+        let mod_dirs: Vec<&PathBuf> = self.mod_dirs.iter().map(|(mod_dir, _)| mod_dir).collect();
+        let settings = format!("{} {} {} {} {:?} {:?} {:?}",
+            cache::CACHE_VERSION, self.rimworld_data_path, self.recursion_limit,
+            self.source_context_lines, active_mods, mod_dirs, self.extension_rules);
+
+        self.parse_cache = Some((path.to_path_buf(), ParseCache::load(path, settings)));
+    }
+
+    /// Parses a file, or takes its defs from the parse cache when its content is unchanged.
+    /// Returns the defs, the content hash when caching, and whether the cache was used.
+    fn parse_cached(&self, path: &Path) -> (Result<Vec<RimWorldDef>>, Option<String>, bool) {
+        let Some((_, cache)) = &self.parse_cache else {
+            return (self.parse_xml_file(path), None, false);
+        };
+
+        let sha256 = match fs::read(path) {
+            Ok(content) => cache::content_hash(&content),
+            Err(e) => return (Err(e.into()), None, false),
+        };
+        match cache.lookup(path, &sha256) {
+            Some(defs) => (Ok(defs), Some(sha256), true),
+            None => (self.parse_xml_file(path), Some(sha256), false),
+        }
+    }
+
+    /// The mod a file belongs to, from the nearest `About/About.xml` in its directory or
+    /// any parent below the filesystem root. Every directory visited on the way up is cached,
+    /// so each About.xml is read once per scan.
+    fn mod_info(&mut self, file_path: &Path) -> Option<ModInfo> {
+        let mut visited = Vec::new();
+        let mut found = None;
+        for dir in file_path.ancestors().skip(1) {
+            if let Some(cached) = self.mod_info_cache.get(dir) {
+                found = cached.clone();
+                break;
+            }
+            visited.push(dir.to_path_buf());
+            if let Some(info) = ModInfo::read(dir).ok().flatten() {
+                found = Some(info);
+                break;
+            }
+        }
+
+        for dir in visited {
+            self.mod_info_cache.insert(dir, found.clone());
+        }
+        found
+    }
+
+    /// Adds a mod folder to scan. Its defs are attributed to the package id in its
+    /// About.xml, or left unattributed with a warning when it has none.
+    pub fn add_mod_dir(&mut self, mod_dir: &Path) -> Result<()> {
+        if !mod_dir.is_dir() {
+            return Err(anyhow::anyhow!("Mod directory does not exist: {}", mod_dir.display()));
+        }
+
+        let info = ModInfo::read(mod_dir)?;
+        match &info {
+            Some(info) => println!("  ✓ Mod {} ({}): {} Defs folders",
+                info.name.as_deref().unwrap_or("unnamed"), info.package_id, mods::def_dirs(mod_dir).len()),
+            None => self.warn(format!("{}: no packageId in About/About.xml, defs are not attributed to a mod", mod_dir.display())),
+        }
+        self.mod_dirs.push((mod_dir.to_path_buf(), info));
+        Ok(())
+    }
+
+    /// Adds the mod folders of `package_ids` found below `mod_roots` and makes the ids the
+    /// load order: a def from a later mod replaces a def of the same type and name from an
+    /// earlier one. Official content is scanned from Data; ids with no folder are skipped.
+    pub fn add_mods_in_load_order(&mut self, package_ids: &[String], mod_roots: &[PathBuf]) -> Result<()> {
+        let found = mods::discover(mod_roots);
+        for package_id in package_ids {
+            let added = self.mod_dirs.iter()
+                .any(|(_, info)| info.as_ref().is_some_and(|info| info.package_id.eq_ignore_ascii_case(package_id)));
+            if added || mods::is_official(package_id) {
+                continue;
+            }
+            if let Some(mod_dir) = found.get(&package_id.to_lowercase()) {
+                self.add_mod_dir(mod_dir)?;
+            }
+        }
+        self.load_order = Some(package_ids.iter().map(|id| id.to_lowercase()).collect());
+        Ok(())
+    }
+
+    fn warn(&mut self, message: String) {
+        eprintln!("  ⚠ {}", message);
+        self.warnings.push(message);
+    }
+
+    /// Every distinct element tag with its number of occurrences, most frequent first.
+    pub fn tag_counts(&self, def_type: Option<&str>) -> Vec<(String, usize)> {
+        fn count_recursive(elements: &[DefElement], counts: &mut HashMap<String, usize>) {
+            for element in elements {
+                *counts.entry(element.name.clone()).or_default() += 1;
+                count_recursive(&element.children, counts);
+            }
+        }
+
+        let mut counts = HashMap::new();
+        for def in &self.parsed_defs {
+            if def_type.is_none_or(|t| t == def.def_type) {
+                count_recursive(&def.elements, &mut counts);
+            }
+        }
+
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+
+    pub fn lint_structure(&mut self) {
+        println!("\nChecking XML structure...");
+
+        let findings: Vec<String> = self.parsed_defs.iter()
+            .flat_map(lint::lint_def)
+            .collect();
+        println!("  ✓ {} structural problems found", findings.len());
+
+        for finding in findings {
+            self.warn(finding);
+        }
+    }
+
+    /// Reparses every def's generated `raw_xml` and warns where the resulting tree differs
+    /// from the parsed one, which points at a bug in `to_xml`.
+    pub fn check_round_trip(&mut self) {
+        println!("\nChecking raw_xml round trips...");
+
+        let mut findings = Vec::new();
+        for def in &self.parsed_defs {
+            let document = format!("<Defs>\n{}</Defs>", def.raw_xml);
+            let problem = match self.parse_xml_str(&document, Path::new(&def.file_path)) {
+                Ok(reparsed) => match reparsed.first() {
+                    Some(reparsed) => first_difference(&def.elements, &reparsed.elements, &def.def_type),
+                    None => Some("no definition found".to_string()),
+                },
+                Err(e) => Some(format!("does not parse: {}", e)),
+            };
+
+            if let Some(problem) = problem {
+                findings.push(format!("{} \"{}\" ({}): raw_xml round trip changes the def: {}",
+                    def.def_type, def.def_name, def.file_path, problem));
+            }
+        }
+        println!("  ✓ {} definitions change on round trip", findings.len());
+
+        for finding in findings {
+            self.warn(finding);
+        }
+    }
+
+    /// Warns about every texture and sound path that doesn't resolve to a file or folder in
+    /// any content root's loose assets, grouped by def and asset kind.
+    pub fn check_assets(&mut self) {
+        println!("\nChecking referenced assets...");
+
+        let mod_dirs: Vec<PathBuf> = self.mod_dirs.iter().map(|(mod_dir, _)| mod_dir.clone()).collect();
+        let roots = assets::AssetRoots::find(&Path::new(&self.rimworld_data_path).join("Data"), &mod_dirs);
+        let mut findings = Vec::new();
+        let mut checked = 0;
+        let mut unchecked = 0;
+        for def in &self.parsed_defs {
+            let mut referenced = assets::referenced_assets(def);
+            referenced.sort();
+            referenced.dedup();
+
+            for (kind, asset_path) in referenced {
+                match roots.exists(kind, &asset_path) {
+                    Some(true) => checked += 1,
+                    Some(false) => {
+                        checked += 1;
+                        findings.push(format!("{} \"{}\" ({}): missing {} {}",
+                            def.def_type, def.def_name, def.file_path, kind.name(), asset_path));
+                    }
+                    None => unchecked += 1,
+                }
+            }
+        }
+        println!("  ✓ {} asset paths checked, {} missing, {} without loose assets to check against",
+            checked, findings.len(), unchecked);
+
+        for finding in findings {
+            self.warn(finding);
+        }
+    }
+
+    pub fn check_deprecated_tags(&mut self, table: &DeprecationTable) {
+        println!("\nChecking for deprecated tags...");
+
+        let findings: Vec<String> = self.parsed_defs.iter()
+            .flat_map(|def| table.check(def))
+            .collect();
+        println!("  ✓ {} deprecated tags found", findings.len());
+
+        for finding in findings {
+            self.warn(finding);
+        }
+    }
+
+    fn detect_extension(&self, file_path: &Path) -> String {
+        let relative_path = file_path.strip_prefix(&self.rimworld_data_path).unwrap_or(file_path);
+
+        self.extension_rules
+            .detect(&file_path.to_string_lossy(), &relative_path.to_string_lossy())
+            // Default to Unknown if we can't determine the extension
+            .unwrap_or("Unknown")
+            .to_string()
+    }
+
+    /// Whether the mods named in the element's `MayRequire` (all of them) and
+    /// `MayRequireAnyOf` (one of them) are active. Always true without an active mod set.
+    fn may_require_satisfied(&self, element: &DefElement) -> bool {
+        let Some(active_mods) = &self.active_mods else {
+            return true;
+        };
+        let package_ids = |value: &str| -> Vec<String> {
+            value.split(',').map(|id| id.trim().to_lowercase()).filter(|id| !id.is_empty()).collect()
+        };
+
+        element.attr("MayRequire").is_none_or(|ids| package_ids(ids).iter().all(|id| active_mods.contains(id)))
+            && element.attr("MayRequireAnyOf").is_none_or(|ids| package_ids(ids).iter().any(|id| active_mods.contains(id)))
+    }
+
+    pub fn parse_xml_file(&self, file_path: &Path) -> Result<Vec<RimWorldDef>> {
+        let content = fs::read_to_string(file_path)?;
+        let mut defs = self.parse_xml_str(&content, file_path)?;
+
+        // Not every filesystem reports a modification time
+        let file_modified = fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(|modified| DateTime::<Utc>::from(modified).to_rfc3339());
+        for def in &mut defs {
+            def.file_modified = file_modified.clone();
+        }
+
+        Ok(defs)
+    }
+
+    /// Parses an in-memory Defs document. `file_path` is only used for the
+    /// extension detection and the relative path stored on each def.
+    pub fn parse_xml_str(&self, content: &str, file_path: &Path) -> Result<Vec<RimWorldDef>> {
+        let mut reader = Reader::from_str(content);
+        reader.trim_text(true);
+
+        let mut defs = Vec::new();
+        let mut buf = Vec::new();
+        let mut element_stack = Vec::new();
+        let mut in_defs = false;
+        let mut seen_root = false;
+        let mut def_start_line = 0;
+        let source_lines: Vec<&str> = if self.source_context_lines > 0 {
+            content.lines().collect()
+        } else {
+            Vec::new()
+        };
+
+        // A self-closing `<x/>` is handled as `<x>` directly followed by `</x>`
+        let mut self_closed: Option<String> = None;
+
+        loop {
+            let event = match self_closed.take() {
+                Some(name) => Ok(Event::End(BytesEnd::new(name))),
+                None => {
+                    buf.clear();
+                    reader.read_event_into(&mut buf)
+                }
+            };
+            match event {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    // Namespace prefixes are dropped so `<x:Defs>` is still recognized
+                    let name = std::str::from_utf8(e.local_name().as_ref()).unwrap_or("").to_string();
+                    if matches!(event, Ok(Event::Empty(_))) {
+                        self_closed = Some(name.clone());
+                    }
+
+                    if self.verbose && !seen_root {
+                        println!("  [trace] document root: <{}>", name);
+                    }
+                    seen_root = true;
+                    
+                    if name == "Defs" {
+                        if self.verbose {
+                            println!("  [trace] in_defs: false -> true");
+                        }
+                        in_defs = true;
+                        continue;
+                    }
+
+                    if in_defs {
+                        if element_stack.len() >= self.recursion_limit {
+                            return Err(anyhow::anyhow!("elements nested deeper than {} levels at line {} (see --recursion-limit)",
+                                self.recursion_limit, line_at(content, reader.buffer_position())));
+                        }
+                        if element_stack.is_empty() {
+                            def_start_line = line_at(content, reader.buffer_position());
+                        }
+
+                        let element = DefElement {
+                            name,
+                            attributes: read_attributes(e),
+                            content: None,
+                            children: Vec::new(),
+                            depth: element_stack.len(),
+                            origin: None,
+                        };
+
+                        element_stack.push(element);
+                    }
+                }
+                Ok(Event::End(ref e)) => {
+                    let name = std::str::from_utf8(e.local_name().as_ref()).unwrap_or("").to_string();
+                    
+                    if name == "Defs" {
+                        if self.verbose {
+                            println!("  [trace] in_defs: true -> false");
+                        }
+                        in_defs = false;
+                        continue;
+                    }
+
+                    if in_defs && !element_stack.is_empty() {
+                        let element = element_stack.pop().unwrap();
+
+                        // Content for mods that aren't active doesn't exist in-game
+                        if !self.may_require_satisfied(&element) {
+                            continue;
+                        }
+                        
+                        if element_stack.is_empty() {
+                            // defName is the def's identity. Name is only the handle children
+                            // inherit through, so it names a def only when there is no defName,
+                            // as for most abstract parents.
+                            let inherit_name = element.attr("Name").map(|v| v.to_string());
+                            let def_name = element.get_content("defName")
+                                .or(inherit_name.as_deref())
+                                .unwrap_or("Unknown").to_string();
+                            
+                            // Direct children of the def root only, so a nested <label> in
+                            // comps or lifeStageAges never stands in for the def's own label
+                            let label = element.get_content("label").map(|v| v.to_string());
+                            let description = element.get_content("description").map(|v| v.to_string());
+                            let parent_name = element.attr("ParentName").map(|v| v.to_string());
+                            let is_abstract = element.attr("Abstract").map(|v| v == "True").unwrap_or(false);
+                            
+                            let tags = self.generate_tags(&element, is_abstract, parent_name.is_some());
+                            let stats = self.calculate_stats(&element.children);
+                            let structured = StructuredFields::extract(&element);
+
+                            // Generate raw XML
+                            let raw_xml = element.to_xml(0);
+
+                            let source_context = (self.source_context_lines > 0).then(|| {
+                                let end_line = line_at(content, reader.buffer_position());
+                                let before_start = def_start_line.saturating_sub(1 + self.source_context_lines);
+                                let after_end = (end_line + self.source_context_lines).min(source_lines.len());
+                                SourceContext {
+                                    before: source_lines[before_start..def_start_line - 1].iter().map(|line| line.to_string()).collect(),
+                                    after: source_lines[end_line.min(after_end)..after_end].iter().map(|line| line.to_string()).collect(),
+                                }
+                            });
+
+                            // Detect extension from file path
+                            let extension = self.detect_extension(file_path);
+
+                            // Make file path relative to RimWorld directory, or to the folder holding the mod
+                            let mod_parent = self.mod_dirs.iter()
+                                .find(|(mod_dir, _)| file_path.starts_with(mod_dir))
+                                .and_then(|(mod_dir, _)| mod_dir.parent());
+                            let relative_path = if let Ok(stripped) = file_path.strip_prefix(&self.rimworld_data_path) {
+                                stripped.to_string_lossy().to_string()
+                            } else if let Some(stripped) = mod_parent.and_then(|parent| file_path.strip_prefix(parent).ok()) {
+                                stripped.to_string_lossy().to_string()
+                            } else {
+                                file_path.file_name().unwrap_or_default().to_string_lossy().to_string()
+                            };
+                            
+                            let rim_def = RimWorldDef {
+                                def_name,
+                                def_type: element.name.clone(),
+                                label,
+                                description,
+                                inherit_name,
+                                parent_name,
+                                is_abstract,
+                                elements: element.children.clone(),
+                                file_path: relative_path,
+                                file_modified: None,
+                                source_line: def_start_line,
+                                sequence: defs.len(),
+                                source_context,
+                                tags,
+                                stats,
+                                references_out: Vec::new(),
+                                references: Vec::new(),
+                                references_in: Vec::new(),
+                                code_references: Vec::new(),
+                                raw_xml,
+                                extension,
+                                source_mod: None,
+                                mod_name: None,
+                                resolved_elements: None,
+                                thing_set_makers: Vec::new(),
+                                obtainable: None,
+                                structured,
+                            };
+
+                            defs.push(rim_def);
+                        } else if let Some(parent) = element_stack.last_mut() {
+                            parent.children.push(element);
+                        }
+                    }
+                }
+                Ok(Event::Text(e)) => set_content(&mut element_stack, e.unescape().unwrap_or_default().trim()),
+                // CDATA is taken verbatim, markup inside it is part of the text
+                Ok(Event::CData(e)) => set_content(&mut element_stack, String::from_utf8_lossy(&e).trim()),
+                // Declarations, processing instructions, comments and doctypes carry no def data
+                Ok(Event::Decl(_)) | Ok(Event::PI(_)) | Ok(Event::Comment(_)) | Ok(Event::DocType(_)) => {}
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(anyhow::anyhow!("Error parsing XML: {}", e)),
+            }
+        }
+
+        Ok(defs)
+    }
+
+    /// All XML files below the Data directory, in walk order.
+    fn xml_files(&self) -> impl Iterator<Item = Result<PathBuf>> + use<> {
+        let mut roots = vec![Path::new(&self.rimworld_data_path).join("Data")];
+        roots.extend(self.mod_dirs.iter().flat_map(|(mod_dir, _)| mods::def_dirs(mod_dir)));
+
+        // Sorted so file order, and with it def order, is the same on every platform
+        roots.into_iter().flat_map(|root| {
+            WalkDir::new(root).sort_by_file_name().into_iter().filter_map(|entry| match entry {
+                Ok(entry) if entry.file_type().is_file() && entry.path().extension().unwrap_or_default() == "xml" => {
+                    Some(Ok(entry.into_path()))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            })
+        })
+    }
+
+    /// Yields definitions file by file without collecting them into `parsed_defs`, so
+    /// memory stays bounded by the largest file. Reference mapping needs the full set,
+    /// which means `references_out`/`references_in` are always empty in streaming mode.
+    pub fn stream_defs(&self) -> impl Iterator<Item = Result<RimWorldDef>> + '_ {
+        self.xml_files().flat_map(move |path| {
+            match path.and_then(|path| self.parse_xml_file(&path)) {
+                Ok(defs) => defs.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(e) => vec![Err(e)],
+            }
+        })
+    }
+
+    /// Definitions per type across the install, reading only each def's type and defName
+    /// without building element trees, for `--count-only`.
+    pub fn count_defs(&self) -> Result<BTreeMap<String, usize>> {
+        let mut counts = BTreeMap::new();
+        for path in self.xml_files() {
+            let path = path?;
+            let content = fs::read_to_string(&path)?;
+            match def_headers(&content) {
+                Ok(headers) => {
+                    for (def_type, _) in headers {
+                        *counts.entry(def_type).or_insert(0) += 1;
+                    }
+                }
+                Err(e) => eprintln!("  ✗ Error parsing {}: {}", path.display(), e),
+            }
+        }
+        Ok(counts)
+    }
+
+    pub fn scan_defs_directory(&mut self) -> Result<()> {
+        let defs_path = Path::new(&self.rimworld_data_path).join("Data");
+        if !self.quiet {
+            println!("Scanning directory: {}", defs_path.display());
+        }
+        
+        let mut file_count = 0;
+        let mut processed_count = 0;
+        let mut error_count = 0;
+        
+        // Files are parsed in parallel; results are merged in walk order so def order and
+        // sequence numbers don't depend on thread scheduling
+        let paths = self.xml_files().collect::<Result<Vec<PathBuf>>>()?;
+        let parsed: Vec<(Result<Vec<RimWorldDef>>, Option<String>, bool)> = paths.par_iter()
+            .map(|path| self.parse_cached(path))
+            .collect();
+
+        let mut cache_entries = Vec::new();
+        for (path, (result, sha256, from_cache)) in paths.into_iter().zip(parsed) {
+            file_count += 1;
+            let initial_def_count = self.parsed_defs.len();
+            
+            match result {
+                Ok(defs) => {
+                    if let Some(sha256) = sha256 {
+                        cache_entries.push((path.clone(), sha256, defs.clone()));
+                        if !from_cache {
+                            self.reparsed_files.push(path.clone());
+                        }
+                    }
+
+                    let file_mod = self.mod_info(&path);
+                    // Document positions become global ones
+                    self.parsed_defs.extend(defs.into_iter().map(|mut def| {
+                        def.sequence += initial_def_count;
+                        if let Some(info) = file_mod.clone().or_else(|| ModInfo::official(&def.extension)) {
+                            def.source_mod = Some(info.package_id);
+                            def.mod_name = info.name;
+                        }
+                        def
+                    }));
+                    processed_count += 1;
+                    let new_defs = self.parsed_defs.len() - initial_def_count;
+                    if new_defs == 0 {
+                        self.files_without_defs.push(path);
+                    } else if !self.quiet {
+                        println!("  ✓ {}: {} definitions", 
+                            path.file_name().unwrap_or_default().to_string_lossy(), 
+                            new_defs);
+                    }
+                },
+                Err(e) => {
+                    error_count += 1;
+                    eprintln!("  ✗ Error parsing {}: {}", path.display(), e);
+                }
+            }
+        }
+        
+        if !self.quiet {
+            println!("\nScan complete:");
+            println!("  Files found: {}", file_count);
+            println!("  Files processed: {}", processed_count);
+            println!("  Errors: {}", error_count);
+            println!("  Total definitions: {}", self.parsed_defs.len());
+        }
+
+        if let Some((cache_path, cache)) = &self.parse_cache {
+            if !self.quiet {
+                println!("  Reused from cache: {} files, {} reparsed",
+                    processed_count - self.reparsed_files.len(), self.reparsed_files.len());
+            }
+            let cache = cache.rebuild(cache_entries);
+            cache.save(cache_path)?;
+            self.parse_cache = Some((cache_path.clone(), cache));
+        }
+        
+        self.apply_load_order();
+
+        // Build reference mappings
+        self.build_reference_mappings();
+        self.inherit_structured_fields();
+        self.link_thing_set_makers();
+        self.classify_obtainable();
+        
+        Ok(())
+    }
+
+    /// Drops every def replaced by a def of the same type and name from a mod later in
+    /// `load_order`, as the game does. Defs of mods outside the load order come first.
+    fn apply_load_order(&mut self) {
+        let Some(load_order) = &self.load_order else {
+            return;
+        };
+        let position = |def: &RimWorldDef| def.source_mod.as_ref()
+            .and_then(|id| load_order.iter().position(|m| m.eq_ignore_ascii_case(id)));
+
+        let mut last: HashMap<(&str, &str), Option<usize>> = HashMap::new();
+        for def in &self.parsed_defs {
+            let entry = last.entry((def.def_type.as_str(), def.def_name.as_str())).or_insert(None);
+            *entry = (*entry).max(position(def));
+        }
+        let keep: Vec<bool> = self.parsed_defs.iter()
+            .map(|def| last[&(def.def_type.as_str(), def.def_name.as_str())] == position(def))
+            .collect();
+
+        let before = self.parsed_defs.len();
+        let mut keep = keep.into_iter();
+        self.parsed_defs.retain(|_| keep.next().unwrap_or(true));
+        if !self.quiet {
+            println!("  ✓ {} definitions overridden by mods later in the load order", before - self.parsed_defs.len());
+        }
+    }
+
+    /// Comps and race properties mostly come from abstract bases, so inheriting defs take
+    /// `structured.comps`, `structured.race` and the "Has Components" tag from their resolved
+    /// tree, whether or not `resolve_inheritance` is called. Needs the Name index from
+    /// `build_reference_mappings`.
+    fn inherit_structured_fields(&mut self) {
+        let resolved: Vec<Option<Vec<DefElement>>> = (0..self.parsed_defs.len())
+            .map(|i| {
+                self.parsed_defs[i].parent_name.as_ref()?;
+                Some(self.resolved_elements(i, &mut Vec::new()))
+            })
+            .collect();
+
+        for (def, elements) in self.parsed_defs.iter_mut().zip(resolved) {
+            let Some(elements) = elements else {
+                continue;
+            };
+            let comps = extract::comps(&elements);
+            if comps.is_some() && !def.tags.iter().any(|tag| tag == "Has Components") {
+                def.tags.push("Has Components".to_string());
+            }
+            def.structured.comps = comps;
+            def.structured.race = extract::race(&elements);
+        }
+    }
+
+    /// Links defs to the ThingSetMakerDefs whose `thingSetMakerTagsToAllow` include one of
+    /// their `thingSetMakerTags`, recording the maker in `thing_set_makers` and as a reference.
+    fn link_thing_set_makers(&mut self) {
+        let mut makers_by_tag: HashMap<String, Vec<String>> = HashMap::new();
+        for def in self.parsed_defs.iter().filter(|def| def.def_type == "ThingSetMakerDef") {
+            let allowed = def.elements.iter().flat_map(|e| e.find_all("thingSetMakerTagsToAllow"));
+            for tag in allowed.flat_map(|list| &list.children).filter_map(|li| li.content.clone()) {
+                let makers = makers_by_tag.entry(tag).or_default();
+                if !makers.contains(&def.def_name) {
+                    makers.push(def.def_name.clone());
+                }
+            }
+        }
+
+        let mut link_count = 0;
+        let mut links: Vec<(usize, String)> = Vec::new();
+        for (idx, def) in self.parsed_defs.iter().enumerate() {
+            let tags = def.structured.thing_set_maker_tags.iter().flatten();
+            let mut makers: Vec<String> = tags.filter_map(|tag| makers_by_tag.get(tag)).flatten().cloned().collect();
+            makers.sort();
+            makers.dedup();
+            links.extend(makers.into_iter().map(|maker| (idx, maker)));
+        }
+
+        for (idx, maker) in links {
+            let def_name = self.parsed_defs[idx].def_name.clone();
+            for &maker_idx in self.def_name_map.get(&maker).into_iter().flatten() {
+                let maker_def = &mut self.parsed_defs[maker_idx];
+                if maker_def.def_type == "ThingSetMakerDef" && !maker_def.references_out.contains(&def_name) {
+                    maker_def.references_out.push(def_name.clone());
+                    maker_def.references.push(Reference::new(&def_name, "thingSetMakerTagsToAllow"));
+                }
+            }
+
+            let def = &mut self.parsed_defs[idx];
+            if !def.references_in.contains(&maker) {
+                def.references_in.push(maker.clone());
+            }
+            def.thing_set_makers.push(maker);
+            link_count += 1;
+        }
+
+        if !self.quiet {
+            println!("  ✓ {} thing set maker links found", link_count);
+        }
+    }
+
+    /// Heuristic for whether a player can actually get a ThingDef, as opposed to debug
+    /// or internal things. A concrete ThingDef is obtainable when any of these hold:
+    /// - `craftable`: it has a `recipeMaker` or is the product of a RecipeDef
+    /// - `buildable`: it has a `designationCategory`
+    /// - `tradeable`: it has `tradeTags` or a `tradeability` of All or Buyable
+    /// - `spawns`: it has `thingSetMakerTags`, a `deepCommonality` or is some building's `mineableThing`
+    /// - `scenario`: a ScenarioDef references it
+    ///
+    /// Fields are read from the resolved tree, so they may come from abstract bases.
+    /// Needs the references and Name index from `build_reference_mappings`.
+    fn classify_obtainable(&mut self) {
+        let resolved: Vec<Option<Vec<DefElement>>> = (0..self.parsed_defs.len())
+            .map(|i| {
+                self.parsed_defs[i].parent_name.as_ref()?;
+                Some(self.resolved_elements(i, &mut Vec::new()))
+            })
+            .collect();
+
+        let mut products = HashSet::new();
+        let mut mined = HashSet::new();
+        let mut scenario_defs = HashSet::new();
+        for (def, elements) in self.parsed_defs.iter().zip(&resolved) {
+            let elements = elements.as_deref().unwrap_or(&def.elements);
+            match def.def_type.as_str() {
+                "RecipeDef" => {
+                    if let Some(recipe_products) = def.elements.iter().find(|e| e.name == "products") {
+                        products.extend(recipe_products.children.iter().map(|product| product.name.clone()));
+                    }
+                }
+                "ScenarioDef" => {
+                    scenario_defs.insert(def.def_name.clone());
+                }
+                _ => {}
+            }
+            mined.extend(field_content(elements, "building/mineableThing").map(|v| v.to_string()));
+        }
+
+        let mut obtainable_count = 0;
+        for (def, elements) in self.parsed_defs.iter_mut().zip(&resolved) {
+            if def.def_type != "ThingDef" || def.is_abstract {
+                continue;
+            }
+
+            let elements = elements.as_deref().unwrap_or(&def.elements);
+            let has = |name: &str| elements.iter().any(|e| e.name == name);
+            let mut via = Vec::new();
+            if has("recipeMaker") || products.contains(&def.def_name) {
+                via.push("craftable".to_string());
+            }
+            if has("designationCategory") {
+                via.push("buildable".to_string());
+            }
+            let tradeability = field_content(elements, "tradeability");
+            if has("tradeTags") || matches!(tradeability, Some("All") | Some("Buyable")) {
+                via.push("tradeable".to_string());
+            }
+            if has("thingSetMakerTags") || has("deepCommonality") || mined.contains(&def.def_name) {
+                via.push("spawns".to_string());
+            }
+            if def.references_in.iter().any(|name| scenario_defs.contains(name)) {
+                via.push("scenario".to_string());
+            }
+
+            if !via.is_empty() {
+                obtainable_count += 1;
+            }
+            def.obtainable = Some(via);
+        }
+
+        if !self.quiet {
+            println!("  ✓ Obtainability classified: {} obtainable things", obtainable_count);
+        }
+    }
+    
+    fn generate_tags(&self, element: &DefElement, is_abstract: bool, has_parent: bool) -> Vec<String> {
+        let mut tags = Vec::new();
+        
+        if is_abstract {
+            tags.push("Abstract".to_string());
+        }
+        
+        if has_parent {
+            tags.push("Inherits".to_string());
+        }
+        
+        // Add tags based on common element names
+        let common_elements: Vec<&str> = element.children.iter().map(|e| e.name.as_str()).collect();
+        
+        if common_elements.contains(&"costList") {
+            tags.push("Craftable".to_string());
+        }
+        
+        if common_elements.contains(&"researchPrerequisites") {
+            tags.push("Research Required".to_string());
+        }
+        
+        if common_elements.contains(&"statBases") {
+            tags.push("Has Stats".to_string());
+        }
+        
+        if common_elements.contains(&"comps") {
+            tags.push("Has Components".to_string());
+        }
+        
+        if common_elements.contains(&"recipes") {
+            tags.push("Has Recipes".to_string());
+        }
+        
+        tags
+    }
+    
+    fn calculate_stats(&self, elements: &[DefElement]) -> Option<DefStats> {
+        if elements.is_empty() {
+            return None;
+        }
+        
+        let element_count = self.count_elements(elements);
+        let max_depth = self.calculate_max_depth(elements, 0);
+        let has_complex_structure = element_count > 20 || max_depth > 4;
+        
+        Some(DefStats {
+            element_count,
+            max_depth,
+            has_complex_structure,
+        })
+    }
+    
+    fn count_elements(&self, elements: &[DefElement]) -> usize {
+        let mut count = 0;
+        let mut pending: Vec<&DefElement> = elements.iter().collect();
+        while let Some(element) = pending.pop() {
+            count += 1;
+            pending.extend(&element.children);
+        }
+        count
+    }
+    
+    fn calculate_max_depth(&self, elements: &[DefElement], current_depth: usize) -> usize {
+        let mut max_depth = current_depth;
+        let mut pending: Vec<(&DefElement, usize)> = elements.iter().map(|e| (e, current_depth + 1)).collect();
+        while let Some((element, depth)) = pending.pop() {
+            max_depth = max_depth.max(depth);
+            pending.extend(element.children.iter().map(|c| (c, depth + 1)));
+        }
+        max_depth
+    }
+    
+    fn build_reference_mappings(&mut self) {
+        if !self.quiet {
+            println!("\nBuilding reference mappings...");
+        }
+        
+        // First pass: build def name index
+        for (idx, def) in self.parsed_defs.iter().enumerate() {
+            self.def_name_map.entry(def.def_name.clone()).or_default().push(idx);
+            if let Some(inherit_name) = &def.inherit_name {
+                self.name_handle_map.entry(inherit_name.clone()).or_default().push(idx);
+            }
+        }
+        
+        // Second pass: extract references and build relationships
+        let mut reference_count = 0;
+        let mut dangling = Vec::new();
+        for i in 0..self.parsed_defs.len() {
+            let def_name = self.parsed_defs[i].def_name.clone();
+            let elements = &self.parsed_defs[i].elements;
+            let (mut references, code_refs) = self.extract_references(elements);
+            for name in self.parsed_defs[i].structured.references() {
+                if !references.iter().any(|reference| reference.def_name == name) {
+                    let path = reference_path(elements, &name).unwrap_or_default();
+                    references.push(Reference::new(&name, &path));
+                }
+            }
+            references.sort();
+            references.dedup();
+
+            for candidate in def_name_candidates(&self.parsed_defs[i]) {
+                if !self.def_name_map.contains_key(&candidate) {
+                    dangling.push((def_name.clone(), candidate));
+                }
+            }
+            
+            // Filter to only valid def names and exclude self-references
+            let valid_fields: Vec<Reference> = references.into_iter()
+                .filter(|reference| {
+                    self.def_name_map.contains_key(&reference.def_name) && reference.def_name != def_name
+                })
+                .collect();
+            let mut valid_refs: Vec<String> = valid_fields.iter().map(|reference| reference.def_name.clone()).collect();
+            valid_refs.dedup();
+            
+            reference_count += valid_refs.len();
+            
+            // Update outgoing references
+            self.parsed_defs[i].references_out = valid_refs.clone();
+            self.parsed_defs[i].references = valid_fields;
+            
+            // Update code references (C# References)
+            self.parsed_defs[i].code_references = code_refs;
+            
+            // Update incoming references for each referenced def
+            for ref_name in valid_refs {
+                if let Some(ref_indices) = self.def_name_map.get(&ref_name) {
+                    // Add the reference to ALL definitions with this name
+                    for &ref_idx in ref_indices {
+                        self.parsed_defs[ref_idx].references_in.push(def_name.clone());
+                    }
+                }
+            }
+        }
+        
+        // Handle parent references, which name the parent's Name attribute rather than its defName
+        for i in 0..self.parsed_defs.len() {
+            if let Some(parent_name) = &self.parsed_defs[i].parent_name.clone() {
+                if let Some(parent_indices) = self.name_handle_map.get(parent_name) {
+                    let child_name = self.parsed_defs[i].def_name.clone();
+                    for &parent_idx in parent_indices {
+                        if !self.parsed_defs[parent_idx].references_in.contains(&child_name) {
+                            self.parsed_defs[parent_idx].references_in.push(child_name.clone());
+                        }
+                    }
+                } else {
+                    // The game refuses to load defs whose parent doesn't exist
+                    let def = &self.parsed_defs[i];
+                    let message = format!("{} \"{}\" ({}): ParentName \"{}\" does not match any Name attribute",
+                        def.def_type, def.def_name, def.file_path, parent_name);
+                    self.warn(message);
+                }
+            }
+        }
+
+        self.find_inheritance_cycles();
+
+        dangling.sort();
+        dangling.dedup();
+        self.dangling_references = dangling;
+        if self.quiet {
+            return;
+        }
+
+        if !self.dangling_references.is_empty() {
+            println!("  ⚠ {} references to undefined definitions", self.dangling_references.len());
+        }
+        println!("  ✓ Reference mappings built: {} references found ({:?} mode)", reference_count, self.reference_mode);
+        if !self.warnings.is_empty() {
+            println!("  ⚠ {} warnings", self.warnings.len());
+        }
+    }
+    
+    /// The def a ParentName points at, preferring a parent of the same def type when names
+    /// are shared across types.
+    fn parent_index(&self, idx: usize) -> Option<usize> {
+        let def = &self.parsed_defs[idx];
+        let indices = self.name_handle_map.get(def.parent_name.as_ref()?)?;
+        indices.iter().copied()
+            .find(|&i| self.parsed_defs[i].def_type == def.def_type)
+            .or_else(|| indices.first().copied())
+    }
+
+    /// Follows every ParentName chain and records each cycle once. The game refuses to load
+    /// cyclic defs; inheritance resolution cuts them where they close.
+    fn find_inheritance_cycles(&mut self) {
+        let mut done = vec![false; self.parsed_defs.len()];
+        let mut cycles = Vec::new();
+
+        for start in 0..self.parsed_defs.len() {
+            let mut path: Vec<usize> = Vec::new();
+            let mut current = Some(start);
+            while let Some(idx) = current.filter(|&idx| !done[idx]) {
+                if let Some(pos) = path.iter().position(|&visited| visited == idx) {
+                    cycles.push(path[pos..].iter().map(|&i| self.parsed_defs[i].def_name.clone()).collect::<Vec<_>>());
+                    break;
+                }
+                path.push(idx);
+                current = self.parent_index(idx);
+            }
+            for idx in path {
+                done[idx] = true;
+            }
+        }
+
+        for cycle in &cycles {
+            eprintln!("  ✗ ParentName cycle: {} -> {}", cycle.join(" -> "), cycle[0]);
+        }
+        self.inheritance_cycles = cycles;
+    }
+
+    /// Stores the ParentName-merged element tree on every def. Needs the def name index
+    /// from `build_reference_mappings`.
+    pub fn resolve_inheritance(&mut self) {
+        if !self.quiet {
+            println!("\nResolving inheritance...");
+        }
+
+        let mut resolved_count = 0;
+        let resolved: Vec<Option<Vec<DefElement>>> = (0..self.parsed_defs.len())
+            .map(|i| {
+                self.parsed_defs[i].parent_name.as_ref()?;
+                resolved_count += 1;
+                Some(self.resolved_elements(i, &mut Vec::new()))
+            })
+            .collect();
+
+        for (def, elements) in self.parsed_defs.iter_mut().zip(resolved) {
+            def.resolved_elements = elements;
+        }
+
+        if !self.quiet {
+            println!("  ✓ {} inheriting definitions resolved", resolved_count);
+        }
+    }
+
+    fn resolved_elements(&self, idx: usize, chain: &mut Vec<usize>) -> Vec<DefElement> {
+        let def = &self.parsed_defs[idx];
+        chain.push(idx);
+
+        let parent_idx = self.parent_index(idx)
+            .filter(|parent_idx| !chain.contains(parent_idx));
+
+        // The def being resolved is the first in the chain, ancestors are named
+        let mut own_elements = def.elements.clone();
+        if self.field_provenance {
+            let origin = if chain.len() == 1 { "self" } else { def.def_name.as_str() };
+            own_elements.iter_mut().for_each(|element| element.set_origin(origin));
+        }
+
+        match parent_idx {
+            Some(parent_idx) => {
+                let parent_elements = self.resolved_elements(parent_idx, chain);
+                inheritance::merge_elements(&parent_elements, &own_elements)
+            }
+            None => own_elements,
+        }
+    }
+
+    fn extract_references(&self, elements: &[DefElement]) -> (Vec<Reference>, Vec<String>) {
+        let mut references = Vec::new();
+        let mut code_references = Vec::new();
+        
+        self.collect_references(elements, &mut references, &mut code_references);
+        
+        // Deduplicate references
+        references.sort();
+        references.dedup();
+        code_references.sort();
+        code_references.dedup();
+        
+        (references, code_references)
+    }
+    
+    fn collect_references(&self, elements: &[DefElement], references: &mut Vec<Reference>, code_references: &mut Vec<String>) {
+        let loose = self.reference_mode == ReferenceMode::Loose;
+
+        // Explicit stack rather than recursion, order doesn't matter since results are sorted.
+        // Each element carries its dotted path from the def root, e.g. `costList.Steel`, and
+        // whether its parent is a map keyed by def names.
+        let mut pending: Vec<(&DefElement, String, bool)> = elements.iter().map(|e| (e, e.name.clone(), false)).collect();
+        while let Some((element, path, in_def_name_map)) = pending.pop() {
+            // Check element name - a def reference in maps like <costList><Steel>10</Steel></costList>,
+            // and possibly anywhere else in loose mode
+            let name_is_reference = in_def_name_map || loose;
+            if name_is_reference && element.name != "defName" && element.name != "li" {
+                references.push(Reference::new(&element.name, &path));
+            }
+            
+            // Check element content - a single token outside free-text fields could be a def reference
+            if let Some(content) = &element.content {
+                // Skip if it's the defName element itself
+                if self.reference_mode != ReferenceMode::Strict
+                    && element.name != "defName"
+                    && !FREE_TEXT_FIELDS.contains(&element.name.as_str())
+                    && !content.trim().contains(char::is_whitespace)
+                {
+                    references.push(Reference::new(content.trim(), &path));
+                }
+            }
+            
+            // Check attributes
+            for (key, value) in &element.attributes {
+                if key == "Class" {
+                    // C# class references
+                    code_references.push(value.clone());
+                } else if loose {
+                    // Other attributes might be def references
+                    references.push(Reference::new(value, &format!("{}@{}", path, key)));
+                }
+            }
+            
+            let is_def_name_map = self.reference_mode != ReferenceMode::Strict && DEF_NAME_MAPS.contains(&element.name.as_str());
+            pending.extend(element.children.iter().map(|child| (child, child_path(&path, child), is_def_name_map)));
+        }
+    }
+}
+
+/// Path of a child element. List items take their list's path, so the content of
+/// `<stuffCategories><li>Metallic</li></stuffCategories>` is found in `stuffCategories`.
+fn child_path(parent_path: &str, child: &DefElement) -> String {
+    if child.name == "li" {
+        parent_path.to_string()
+    } else {
+        format!("{}.{}", parent_path, child.name)
+    }
+}
+
+/// An outgoing reference and the element it was found in, as a dotted path from the def
+/// root (`costList.Steel`, `stuffCategories.li`); attributes are appended as `@name`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Reference {
+    pub def_name: String,
+    pub field_path: String,
+}
+
+impl Reference {
+    pub fn new(def_name: &str, field_path: &str) -> Self {
+        Self {
+            def_name: def_name.to_string(),
+            field_path: field_path.to_string(),
+        }
+    }
+}
+
+/// Dotted path of the first element naming `def_name`, by content or by element name.
+/// Locates references found by structured extraction, which doesn't track paths.
+fn reference_path(elements: &[DefElement], def_name: &str) -> Option<String> {
+    let mut pending: Vec<(&DefElement, String)> = elements.iter().rev().map(|e| (e, e.name.clone())).collect();
+    while let Some((element, path)) = pending.pop() {
+        if element.content.as_deref().map(str::trim) == Some(def_name) || element.name == def_name {
+            return Some(path);
+        }
+        pending.extend(element.children.iter().rev().map(|child| (child, child_path(&path, child))));
+    }
+    None
+}
+
+/// Parses every def of the install at `rimworld_path` (the folder holding `Data`) with
+/// default settings. References are mapped, inheritance is not resolved.
+pub fn parse_defs(rimworld_path: &Path) -> Result<Vec<RimWorldDef>> {
+    let mut parser = DefParser::new(rimworld_path.to_string_lossy().to_string());
+    parser.scan_defs_directory()?;
+    Ok(parser.parsed_defs)
+}
+
+/// (def type, defName) of every def in a Defs document, tracking only the nesting depth
+/// instead of building elements.
+fn def_headers(content: &str) -> Result<Vec<(String, Option<String>)>> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut headers: Vec<(String, Option<String>)> = Vec::new();
+    let mut buf = Vec::new();
+    let mut depth = 0;           // Nesting below <Defs>, 1 = def root
+    let mut in_defs = false;
+    let mut in_def_name = false;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                let name = std::str::from_utf8(e.local_name().as_ref()).unwrap_or("").to_string();
+                if !in_defs {
+                    in_defs = name == "Defs";
+                    continue;
+                }
+                depth += 1;
+                if depth == 1 {
+                    let def_name = read_attributes(&e).shift_remove("Name");
+                    headers.push((name, def_name));
+                } else if depth == 2 && name == "defName" {
+                    in_def_name = true;
+                }
+            }
+            // A self-closing def root, usually an abstract parent without fields
+            Event::Empty(e) if in_defs && depth == 0 => {
+                let name = std::str::from_utf8(e.local_name().as_ref()).unwrap_or("").to_string();
+                headers.push((name, read_attributes(&e).shift_remove("Name")));
+            }
+            Event::End(_) if in_defs => {
+                if depth == 0 {
+                    in_defs = false;
+                } else {
+                    depth -= 1;
+                }
+                in_def_name = false;
+            }
+            Event::Text(e) if in_def_name => {
+                if let Some((_, def_name)) = headers.last_mut() {
+                    *def_name = Some(e.unescape()?.trim().to_string());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(headers)
+}
+
+/// Names in a def that are meant to be defNames: values of fields known to hold def
+/// names, `...Def` elements and entries of `...Defs` lists. Only PascalCase tokens
+/// qualify, so numbers, booleans and free text are never taken for a def name.
+fn def_name_candidates(def: &RimWorldDef) -> Vec<String> {
+    let mut candidates = def.structured.references();
+
+    let mut pending: Vec<(&DefElement, &str)> = def.elements.iter().map(|e| (e, def.def_type.as_str())).collect();
+    while let Some((element, parent_name)) = pending.pop() {
+        let holds_def = element.name.ends_with("Def") || (element.name == "li" && parent_name.ends_with("Defs"));
+        if let Some(content) = element.content.as_deref().filter(|_| holds_def && element.name != "defName") {
+            candidates.push(content.trim().to_string());
+        }
+        pending.extend(element.children.iter().map(|child| (child, element.name.as_str())));
+    }
+
+    candidates.retain(|candidate| looks_like_def_name(candidate) && *candidate != def.def_name);
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+fn looks_like_def_name(value: &str) -> bool {
+    value.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !matches!(value, "True" | "False" | "None" | "Null")
+}
+
+/// Describes the first place where two element lists differ, by element path.
+fn first_difference(original: &[DefElement], reparsed: &[DefElement], path: &str) -> Option<String> {
+    if original.len() != reparsed.len() {
+        return Some(format!("{}: {} children became {}", path, original.len(), reparsed.len()));
+    }
+
+    for (a, b) in original.iter().zip(reparsed) {
+        let element_path = format!("{}/{}", path, a.name);
+        if a.name != b.name {
+            return Some(format!("{}: renamed to <{}>", element_path, b.name));
+        }
+        if a.attributes != b.attributes {
+            return Some(format!("{}: attributes {:?} became {:?}", element_path, a.attributes, b.attributes));
+        }
+        if a.content != b.content {
+            return Some(format!("{}: content {:?} became {:?}", element_path, a.content, b.content));
+        }
+        if let Some(difference) = first_difference(&a.children, &b.children, &element_path) {
+            return Some(difference);
+        }
+    }
+
+    None
+}
+
+/// Stores non-empty text as the content of the innermost open element.
+fn set_content(element_stack: &mut [DefElement], text: &str) {
+    if let Some(element) = element_stack.last_mut().filter(|_| !text.is_empty()) {
+        element.content = Some(text.to_string());
+    }
+}
+
+/// Attributes of a start tag by local name. Namespace declarations are skipped.
+fn read_attributes(e: &BytesStart) -> IndexMap<String, String> {
+    let mut attributes = IndexMap::new();
+    for attr in e.attributes().flatten() {
+        let qualified = std::str::from_utf8(attr.key.as_ref()).unwrap_or("");
+        if qualified == "xmlns" || qualified.starts_with("xmlns:") {
+            continue;
+        }
+        let key = std::str::from_utf8(attr.key.local_name().as_ref()).unwrap_or("").to_string();
+        // Stored unescaped like text content, to_xml escapes both again
+        let value = match attr.unescape_value() {
+            Ok(value) => value.to_string(),
+            Err(_) => String::from_utf8_lossy(&attr.value).to_string(),
+        };
+        attributes.insert(key, value);
+    }
+    attributes
+}
+
+/// Writes an output artifact, creating missing parent directories first.
+pub fn write_output(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// 1-based line number of a byte offset into `content`.
+fn line_at(content: &str, position: usize) -> usize {
+    content.as_bytes()[..position.min(content.len())].iter().filter(|&&b| b == b'\n').count() + 1
+}
+
+/// Canonical spelling of a plain decimal number (`.5`, `0.50` -> `0.5`, `1.0` -> `1`,
+/// `-0` -> `0`). Integers are rewritten exactly, or kept as written when too large.
+/// Returns None for anything that isn't one, including ranges like `1~3` and exponents.
+pub fn normalize_number(value: &str) -> Option<String> {
+    let value = value.trim();
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+
+    if !digits.chars().any(|c| c.is_ascii_digit())
+        || !digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        || digits.matches('.').count() > 1 {
+        return None;
+    }
+
+    // Integers above 2^53 have no exact f64, and rounding them would change seeds and IDs
+    if !digits.contains('.') {
+        return Some(value.parse::<i128>().map_or_else(|_| value.to_string(), |number| number.to_string()));
+    }
+
+    let number: f64 = value.parse().ok()?;
+    // -0.0 would print as "-0"
+    Some(format!("{}", if number == 0.0 { 0.0 } else { number }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A RimWorld install in a temporary directory with `files` at their relative paths.
+    fn install(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (relative_path, content) in files {
+            let path = dir.path().join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    /// Scans the install quietly, with references mapped.
+    fn scanned(dir: &tempfile::TempDir) -> DefParser {
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.scan_defs_directory().unwrap();
+        parser
+    }
+
+    fn about(package_id: &str) -> String {
+        format!("<ModMetaData><packageId>{}</packageId></ModMetaData>", package_id)
+    }
+
+    #[test]
+    fn later_mods_in_the_load_order_override_defs() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName><label>core</label></ThingDef></Defs>"),
+            ("Mods/First/About/About.xml", &about("Author.First")),
+            ("Mods/First/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName><label>first</label></ThingDef></Defs>"),
+            ("Mods/Second/About/About.xml", &about("Author.Second")),
+            ("Mods/Second/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName><label>second</label></ThingDef></Defs>"),
+            ("Mods/Inactive/About/About.xml", &about("Author.Inactive")),
+            ("Mods/Inactive/Defs/Items.xml", "<Defs><ThingDef><defName>Plasteel</defName></ThingDef></Defs>"),
+        ]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        let load_order: Vec<String> = ["Ludeon.RimWorld", "Author.Second", "Author.First"].map(String::from).into();
+        parser.add_mods_in_load_order(&load_order, &[dir.path().join("Mods")]).unwrap();
+        parser.scan_defs_directory().unwrap();
+
+        let defs: Vec<(&str, Option<&str>)> = parser.parsed_defs.iter()
+            .map(|def| (def.def_name.as_str(), def.label.as_deref()))
+            .collect();
+        assert_eq!(defs, [("Steel", Some("first"))]);
+    }
+
+    #[test]
+    fn check_assets_searches_mod_texture_folders() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", "<Defs/>"),
+            ("Mods/Guns/About/About.xml", &about("Author.Guns")),
+            ("Mods/Guns/Defs/Items.xml", r#"<Defs>
+                <ThingDef><defName>Gun_Laser</defName><graphicData><texPath>Things/Laser</texPath></graphicData></ThingDef>
+                <ThingDef><defName>Gun_Plasma</defName><graphicData><texPath>Things/Plasma</texPath></graphicData></ThingDef>
+            </Defs>"#),
+            ("Mods/Guns/Textures/Things/Laser.png", ""),
+        ]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.add_mod_dir(&dir.path().join("Mods/Guns")).unwrap();
+        parser.scan_defs_directory().unwrap();
+        parser.check_assets();
+
+        assert_eq!(parser.warnings.len(), 1);
+        assert!(parser.warnings[0].contains("Gun_Plasma") && parser.warnings[0].contains("Things/Plasma"));
+    }
+
+    #[test]
+    fn count_defs_matches_a_full_parse_without_building_trees() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", r#"<Defs>
+                <ThingDef Name="BaseItem" Abstract="True"><stackLimit>75</stackLimit></ThingDef>
+                <ThingDef ParentName="BaseItem"><defName>Steel</defName></ThingDef>
+                <RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef>
+            </Defs>"#),
+            ("Mods/Guns/About/About.xml", &about("Author.Guns")),
+            ("Mods/Guns/Defs/Items.xml", "<Defs><ThingDef><defName>Gun_Laser</defName></ThingDef></Defs>"),
+        ]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.add_mod_dir(&dir.path().join("Mods/Guns")).unwrap();
+        let counts = parser.count_defs().unwrap();
+        assert!(parser.parsed_defs.is_empty());
+
+        parser.scan_defs_directory().unwrap();
+        let mut parsed: BTreeMap<String, usize> = BTreeMap::new();
+        for def in &parser.parsed_defs {
+            *parsed.entry(def.def_type.clone()).or_default() += 1;
+        }
+        assert_eq!(counts, parsed);
+        assert_eq!(counts["ThingDef"], 3);
+    }
+
+    #[test]
+    fn cache_reparses_only_changed_files() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>"),
+            ("Data/Core/Defs/Recipes.xml", "<Defs><RecipeDef><defName>Make_Steel</defName></RecipeDef></Defs>"),
+        ]);
+        let cache_path = dir.path().join("parse-cache.zstd");
+        let scan = || {
+            let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+            parser.quiet = true;
+            parser.enable_cache(&cache_path);
+            parser.scan_defs_directory().unwrap();
+            parser
+        };
+
+        assert_eq!(scan().reparsed_files.len(), 2);
+        let recipes_path = dir.path().join("Data/Core/Defs/Recipes.xml");
+        fs::write(&recipes_path, "<Defs><RecipeDef><defName>Make_Plasteel</defName></RecipeDef></Defs>").unwrap();
+
+        let parser = scan();
+        assert_eq!(parser.reparsed_files, [recipes_path]);
+        let def_names: Vec<&str> = parser.parsed_defs.iter().map(|def| def.def_name.as_str()).collect();
+        assert_eq!(def_names, ["Steel", "Make_Plasteel"]);
+    }
+
+    #[test]
+    fn normalize_number_canonicalizes_decimals_exactly() {
+        assert_eq!(normalize_number(".5"), normalize_number("0.50"));
+        assert_eq!(normalize_number("0.50").as_deref(), Some("0.5"));
+        assert_eq!(normalize_number("1.0").as_deref(), Some("1"));
+        assert_eq!(normalize_number("-0").as_deref(), Some("0"));
+        assert_eq!(normalize_number("-0.0").as_deref(), Some("0"));
+        assert_eq!(normalize_number("9007199254740993").as_deref(), Some("9007199254740993"));
+        assert_eq!(normalize_number("123456789012345678901234567890123456789012").as_deref(),
+            Some("123456789012345678901234567890123456789012"));
+        assert_eq!(normalize_number("1~3"), None);
+        assert_eq!(normalize_number("1e5"), None);
+    }
+
+    #[test]
+    fn inherit_false_is_not_kept_on_resolved_elements() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="BaseItem" Abstract="True">
+                <comps><li>CompForbiddable</li></comps>
+            </ThingDef>
+            <ThingDef ParentName="BaseItem">
+                <defName>Steel</defName>
+                <comps Inherit="False"><li>CompQuality</li></comps>
+                <tradeTags Inherit="False"><li>Raw</li></tradeTags>
+            </ThingDef>
+        </Defs>"#)]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.scan_defs_directory().unwrap();
+        parser.resolve_inheritance();
+
+        let steel = parser.parsed_defs.iter().find(|def| def.def_name == "Steel").unwrap();
+        let resolved = steel.resolved_elements.as_ref().unwrap();
+        for name in ["comps", "tradeTags"] {
+            let element = resolved.iter().find(|element| element.name == name).unwrap();
+            assert_eq!(element.attr("Inherit"), None, "{}", name);
+            assert_eq!(element.children.len(), 1, "{}", name);
+        }
+    }
+
+    #[test]
+    fn parent_names_resolve_across_mods_and_missing_ones_warn() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", r#"<Defs><ThingDef Name="ResourceBase" Abstract="True"><stackLimit>75</stackLimit></ThingDef></Defs>"#),
+            ("Mods/Metals/About/About.xml", &about("Author.Metals")),
+            ("Mods/Metals/Defs/Items.xml", r#"<Defs>
+                <ThingDef ParentName="ResourceBase"><defName>Titanium</defName></ThingDef>
+                <ThingDef ParentName="MetalBase"><defName>Tin</defName></ThingDef>
+            </Defs>"#),
+        ]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.add_mod_dir(&dir.path().join("Mods/Metals")).unwrap();
+        parser.scan_defs_directory().unwrap();
+
+        let base = parser.parsed_defs.iter().find(|def| def.inherit_name.as_deref() == Some("ResourceBase")).unwrap();
+        assert_eq!(base.references_in, ["Titanium"]);
+        assert_eq!(parser.warnings.len(), 1);
+        assert!(parser.warnings[0].contains("\"Tin\"") && parser.warnings[0].contains("ParentName \"MetalBase\""));
+    }
+
+    #[test]
+    fn element_query_helpers() {
+        let defs = DefParser::new(String::new()).parse_xml_str(r#"<Defs>
+            <ThingDef>
+                <defName>Gun_Revolver</defName>
+                <statBases><MarketValue>150</MarketValue></statBases>
+                <verbs>
+                    <li Class="VerbProperties"><verbClass>Verb_Shoot</verbClass><range>25.9</range></li>
+                    <li Class="VerbProperties"><verbClass>Verb_Melee</verbClass></li>
+                </verbs>
+            </ThingDef>
+        </Defs>"#, Path::new("Data/Core/Defs/Weapons.xml")).unwrap();
+        let def = &defs[0];
+
+        let verbs = def.elements.iter().find(|element| element.name == "verbs").unwrap();
+        let first = verbs.find_child("li").unwrap();
+        assert_eq!(first.attr("Class"), Some("VerbProperties"));
+        assert_eq!(first.attr("MayRequire"), None);
+        assert_eq!(first.get_content("verbClass"), Some("Verb_Shoot"));
+        assert_eq!(verbs.find_all("verbClass").iter().map(|e| e.content.as_deref()).collect::<Vec<_>>(),
+            [Some("Verb_Shoot"), Some("Verb_Melee")]);
+        assert_eq!(verbs.get_content("li.range"), Some("25.9"));
+        assert_eq!(verbs.get_content("li.missing"), None);
+
+        assert_eq!(def.effective_field("statBases.MarketValue"), Some("150"));
+        assert_eq!(def.effective_field("statBases/MarketValue"), Some("150"));
+        assert!(def.has_element("range"));
+        assert!(!def.has_element("Mass"));
+    }
+
+    #[test]
+    fn deprecated_tags_are_flagged_with_their_replacement() {
+        let defs = DefParser::new(String::new()).parse_xml_str(r#"<Defs>
+            <ThingDef>
+                <defName>Meal_Simple</defName>
+                <ingestible><nutrition>0.9</nutrition></ingestible>
+                <comps><li><nutrition>1</nutrition></li></comps>
+            </ThingDef>
+        </Defs>"#, Path::new("Data/Core/Defs/Meals.xml")).unwrap();
+
+        let findings = DeprecationTable::load(None).unwrap().check(&defs[0]);
+        assert_eq!(findings, [
+            "ThingDef \"Meal_Simple\" (Data/Core/Defs/Meals.xml): <ingestible><nutrition> is deprecated since 1.0, use statBases.Nutrition instead",
+        ]);
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected_at_the_recursion_limit() {
+        let nested = |depth: usize| format!("<Defs><ThingDef><defName>Deep</defName>{}x{}</ThingDef></Defs>",
+            "<a>".repeat(depth), "</a>".repeat(depth));
+        let parser = DefParser::new(String::new());
+        let path = Path::new("Data/Core/Defs/Deep.xml");
+
+        let error = parser.parse_xml_str(&nested(100_000), path).unwrap_err();
+        assert!(error.to_string().contains(&format!("nested deeper than {} levels", DEFAULT_RECURSION_LIMIT)));
+
+        // Just below the limit every tree walk still has to work
+        let defs = parser.parse_xml_str(&nested(DEFAULT_RECURSION_LIMIT - 2), path).unwrap();
+        assert!(defs[0].has_element("a"));
+        assert!(defs[0].raw_xml.contains("x"));
+    }
+
+    #[test]
+    fn to_xml_escapes_special_characters_and_round_trips() {
+        let parser = DefParser::new(String::new());
+        let path = Path::new("Data/Core/Defs/Text.xml");
+        let defs = parser.parse_xml_str(r#"<Defs>
+            <ThingDef>
+                <defName>Sign</defName>
+                <description note="&quot;a&quot; &amp; 'b'">Fish &amp; chips &lt;hot&gt; "fresh" 'daily'</description>
+            </ThingDef>
+        </Defs>"#, path).unwrap();
+        let description = defs[0].elements.iter().find(|element| element.name == "description").unwrap();
+        assert_eq!(description.content.as_deref(), Some(r#"Fish & chips <hot> "fresh" 'daily'"#));
+
+        let xml = description.to_xml(0);
+        assert!(xml.contains("&amp;") && xml.contains("&lt;hot&gt;"));
+        let reparsed = parser.parse_xml_str(&format!("<Defs><ThingDef><defName>Sign</defName>{}</ThingDef></Defs>", xml), path).unwrap();
+        let round_trip = reparsed[0].elements.iter().find(|element| element.name == "description").unwrap();
+        assert_eq!(round_trip.content, description.content);
+        assert_eq!(round_trip.attr("note"), Some(r#""a" & 'b'"#));
+    }
+
+    #[test]
+    fn cdata_content_is_captured() {
+        let defs = DefParser::new(String::new()).parse_xml_str(r#"<Defs>
+            <ThingDef>
+                <defName>Note</defName>
+                <description><![CDATA[Use <b>only</b> in emergencies & "never" indoors]]></description>
+            </ThingDef>
+        </Defs>"#, Path::new("Data/Core/Defs/Notes.xml")).unwrap();
+
+        assert_eq!(defs[0].description.as_deref(), Some(r#"Use <b>only</b> in emergencies & "never" indoors"#));
+    }
+
+    #[test]
+    fn three_def_inheritance_cycle_is_detected() {
+        let dir = install(&[("Data/Core/Defs/Cycle.xml", r#"<Defs>
+            <ThingDef Name="A" ParentName="B"><defName>A</defName></ThingDef>
+            <ThingDef Name="B" ParentName="C"><defName>B</defName></ThingDef>
+            <ThingDef Name="C" ParentName="A"><defName>C</defName></ThingDef>
+            <ThingDef ParentName="A"><defName>D</defName></ThingDef>
+        </Defs>"#)]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.scan_defs_directory().unwrap();
+        assert_eq!(parser.inheritance_cycles, [["A", "B", "C"]]);
+
+        // Resolving has to terminate despite the cycle
+        parser.resolve_inheritance();
+    }
+
+    #[test]
+    fn references_record_the_field_they_came_from() {
+        let dir = install(&[("Data/Core/Defs/Buildings.xml", r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef><defName>ComponentIndustrial</defName></ThingDef>
+            <ThingDef>
+                <defName>Wall</defName>
+                <costList><Steel>5</Steel></costList>
+                <building><blueprintMaterial>ComponentIndustrial</blueprintMaterial></building>
+            </ThingDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+
+        let wall = parser.parsed_defs.iter().find(|def| def.def_name == "Wall").unwrap();
+        assert_eq!(wall.references, [
+            Reference::new("ComponentIndustrial", "building.blueprintMaterial"),
+            Reference::new("Steel", "costList.Steel"),
+        ]);
+    }
+
+    #[test]
+    fn free_text_does_not_reference_defs() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef>
+                <defName>Ingot</defName>
+                <label>Steel</label>
+                <description>Steel</description>
+            </ThingDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+
+        let ingot = parser.parsed_defs.iter().find(|def| def.def_name == "Ingot").unwrap();
+        assert!(ingot.references_out.is_empty(), "{:?}", ingot.references_out);
+        let steel = parser.parsed_defs.iter().find(|def| def.def_name == "Steel").unwrap();
+        assert!(steel.references_in.is_empty());
+    }
+
+    #[test]
+    fn list_items_stay_separate_and_reference_their_content() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <StuffCategoryDef><defName>Metallic</defName></StuffCategoryDef>
+            <StuffCategoryDef><defName>Woody</defName></StuffCategoryDef>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef>
+                <defName>Table</defName>
+                <stuffCategories><li>Metallic</li><li>Woody</li></stuffCategories>
+                <costList><Steel>20</Steel></costList>
+            </ThingDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+
+        let table = parser.parsed_defs.iter().find(|def| def.def_name == "Table").unwrap();
+        let categories = table.elements.iter().find(|element| element.name == "stuffCategories").unwrap();
+        let items: Vec<Option<&str>> = categories.children.iter().map(|li| li.content.as_deref()).collect();
+        assert_eq!(items, [Some("Metallic"), Some("Woody")]);
+        assert_eq!(table.references, [
+            Reference::new("Metallic", "stuffCategories"),
+            Reference::new("Steel", "costList.Steel"),
+            Reference::new("Woody", "stuffCategories"),
+        ]);
+    }
+
+    #[test]
+    fn defs_are_identified_by_def_name_or_name_attribute() {
+        let defs = DefParser::new(String::new()).parse_xml_str(r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True"><stackLimit>75</stackLimit></ThingDef>
+            <ThingDef Name="SteelBase" ParentName="ResourceBase"><defName>Steel</defName></ThingDef>
+        </Defs>"#, Path::new("Data/Core/Defs/Items.xml")).unwrap();
+
+        let identities: Vec<(&str, Option<&str>, bool)> = defs.iter()
+            .map(|def| (def.def_name.as_str(), def.inherit_name.as_deref(), def.is_abstract))
+            .collect();
+        assert_eq!(identities, [
+            ("ResourceBase", Some("ResourceBase"), true),
+            ("Steel", Some("SteelBase"), false),
+        ]);
+    }
+
+    #[test]
+    fn parent_name_matches_the_name_attribute_not_the_def_name() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="MetalBase"><defName>Metal</defName><stackLimit>75</stackLimit></ThingDef>
+            <ThingDef><defName>MetalBase</defName><stackLimit>1</stackLimit></ThingDef>
+            <ThingDef ParentName="MetalBase"><defName>Steel</defName></ThingDef>
+        </Defs>"#)]);
+        let mut parser = scanned(&dir);
+        parser.resolve_inheritance();
+
+        let references_in = |def_name: &str| parser.parsed_defs.iter()
+            .find(|def| def.def_name == def_name).unwrap().references_in.clone();
+        assert_eq!(references_in("Metal"), ["Steel"]);
+        assert!(references_in("MetalBase").is_empty());
+        let steel = parser.parsed_defs.iter().find(|def| def.def_name == "Steel").unwrap();
+        assert_eq!(steel.effective_field("stackLimit"), Some("75"));
+    }
+
+    #[test]
+    fn raw_xml_round_trips_entities_attributes_cdata_and_self_closing_tags() {
+        let dir = install(&[("Data/Core/Defs/Weapons.xml", r#"<Defs>
+            <ThingDef Name="BaseGun" Abstract="True"/>
+            <ThingDef ParentName="BaseGun">
+                <defName>Gun_Revolver</defName>
+                <label>revolver &amp; "holster"</label>
+                <description><![CDATA[Six <shots>, then reload]]></description>
+                <comps>
+                    <li Class="CompProperties_Forbiddable"/>
+                    <li Class="CompProperties_Styleable" MayRequire="Ludeon.RimWorld.Ideology"></li>
+                </comps>
+                <tradeTags/>
+            </ThingDef>
+        </Defs>"#)]);
+        let mut parser = scanned(&dir);
+
+        let names: Vec<&str> = parser.parsed_defs.iter().map(|def| def.def_name.as_str()).collect();
+        assert_eq!(names, ["BaseGun", "Gun_Revolver"]);
+        let revolver = &parser.parsed_defs[1];
+        let comps = revolver.elements.iter().find(|element| element.name == "comps").unwrap();
+        let classes: Vec<Option<&str>> = comps.children.iter().map(|li| li.attr("Class")).collect();
+        assert_eq!(classes, [Some("CompProperties_Forbiddable"), Some("CompProperties_Styleable")]);
+        assert!(revolver.has_element("tradeTags"));
+        assert_eq!(parser.count_defs().unwrap()["ThingDef"], 2);
+
+        parser.check_round_trip();
+        assert!(parser.warnings.is_empty(), "{:?}", parser.warnings);
+    }
+
+    #[test]
+    fn comps_are_inherited_without_resolving_inheritance() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True">
+                <comps>
+                    <li Class="CompProperties_Forbiddable"/>
+                    <li><compClass>CompColorable</compClass></li>
+                </comps>
+            </ThingDef>
+            <ThingDef ParentName="ResourceBase"><defName>Steel</defName></ThingDef>
+            <ThingDef ParentName="ResourceBase">
+                <defName>Cloth</defName>
+                <comps><li Class="CompProperties_Rottable"/></comps>
+            </ThingDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+
+        let def = |def_name: &str| parser.parsed_defs.iter().find(|def| def.def_name == def_name).unwrap();
+        assert_eq!(def("Steel").structured.comps.as_deref(), Some(&["CompProperties_Forbiddable".to_string(), "CompColorable".to_string()][..]));
+        assert!(def("Steel").tags.iter().any(|tag| tag == "Has Components"));
+        assert_eq!(def("Cloth").structured.comps.as_ref().map(Vec::len), Some(3));
+        assert!(def("Steel").resolved_elements.is_none());
+    }
+
+    #[test]
+    fn race_kind_is_classified_from_the_inherited_race() {
+        let dir = install(&[("Data/Core/Defs/Races.xml", r#"<Defs>
+            <ThingDef Name="BaseMechanoid" Abstract="True">
+                <race><intelligence>ToolUser</intelligence><fleshType>Mechanoid</fleshType></race>
+            </ThingDef>
+            <ThingDef ParentName="BaseMechanoid">
+                <defName>Mech_Scyther</defName>
+                <race><body>Scyther</body></race>
+            </ThingDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+
+        let race = parser.parsed_defs.iter().find(|def| def.def_name == "Mech_Scyther").unwrap()
+            .structured.race.clone().unwrap();
+        assert_eq!(race.kind, extract::PawnKind::Mechanoid);
+        assert_eq!(race.body.as_deref(), Some("Scyther"));
+    }
+
+    #[test]
+    fn thing_set_makers_are_linked_to_the_defs_they_allow() {
+        let dir = install(&[("Data/Core/Defs/Rewards.xml", r#"<Defs>
+            <ThingDef><defName>Gun_ChargeRifle</defName><thingSetMakerTags><li>RewardStandardHighFreq</li></thingSetMakerTags></ThingDef>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingSetMakerDef><defName>Reward_ItemsStandard</defName><root><fixedParams><filter>
+                <thingSetMakerTagsToAllow><li>RewardStandardHighFreq</li></thingSetMakerTagsToAllow>
+            </filter></fixedParams></root></ThingSetMakerDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+        let def = |name: &str| parser.parsed_defs.iter().find(|def| def.def_name == name).unwrap();
+
+        assert_eq!(def("Gun_ChargeRifle").thing_set_makers, ["Reward_ItemsStandard"]);
+        assert!(def("Steel").thing_set_makers.is_empty());
+        let maker = def("Reward_ItemsStandard");
+        assert!(maker.references_out.contains(&"Gun_ChargeRifle".to_string()));
+        assert!(maker.references.iter().any(|r| r.def_name == "Gun_ChargeRifle" && r.field_path == "thingSetMakerTagsToAllow"));
+        assert!(def("Gun_ChargeRifle").references_in.contains(&"Reward_ItemsStandard".to_string()));
+    }
+
+    #[test]
+    fn stream_defs_yields_every_def_without_references() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName></ThingDef><ThingDef><defName>Gold</defName></ThingDef></Defs>"),
+            ("Data/Core/Defs/Recipes.xml", "<Defs><RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef></Defs>"),
+        ]);
+        let parser = DefParser::new(dir.path().to_string_lossy().to_string());
+
+        let defs: Vec<RimWorldDef> = parser.stream_defs().collect::<Result<_>>().unwrap();
+        let mut names: Vec<&str> = defs.iter().map(|def| def.def_name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["Gold", "Make_Steel", "Steel"]);
+        assert!(defs.iter().all(|def| def.references_out.is_empty() && def.references_in.is_empty()));
+        assert!(parser.parsed_defs.is_empty());
+    }
+
+    #[test]
+    fn namespaced_tags_and_processing_instructions() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <?xml-stylesheet type="text/xsl" href="defs.xsl"?>
+            <!DOCTYPE Defs>
+            <r:Defs xmlns:r="urn:rimworld">
+                <?editor fold?>
+                <r:ThingDef><r:defName>Steel</r:defName><r:label>steel</r:label></r:ThingDef>
+            </r:Defs>"#;
+        let defs = DefParser::new(String::new()).parse_xml_str(xml, Path::new("Items.xml")).unwrap();
+
+        assert_eq!(defs.len(), 1);
+        assert_eq!((defs[0].def_type.as_str(), defs[0].def_name.as_str()), ("ThingDef", "Steel"));
+        assert_eq!(defs[0].label.as_deref(), Some("steel"));
+    }
+
+    #[test]
+    fn effective_field_reads_inherited_and_overridden_values() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True">
+                <statBases><MarketValue>1</MarketValue><Mass>0.5</Mass></statBases>
+            </ThingDef>
+            <ThingDef ParentName="ResourceBase">
+                <defName>Steel</defName>
+                <statBases><MarketValue>1.9</MarketValue></statBases>
+            </ThingDef>
+        </Defs>"#)]);
+        let mut parser = scanned(&dir);
+        let steel = |parser: &DefParser| parser.parsed_defs.iter().find(|def| def.def_name == "Steel").unwrap().clone();
+
+        assert_eq!(steel(&parser).effective_field("statBases/Mass"), None);
+
+        parser.resolve_inheritance();
+        let steel = steel(&parser);
+        assert_eq!(steel.effective_field("statBases/Mass"), Some("0.5"));
+        assert_eq!(steel.effective_field("statBases/MarketValue"), Some("1.9"));
+        assert_eq!(steel.effective_field("statBases.MarketValue"), Some("1.9"));
+        assert_eq!(steel.effective_field("defName"), Some("Steel"));
+        assert_eq!(steel.effective_field("statBases/Beauty"), None);
+    }
+
+    #[test]
+    fn tag_counts_by_frequency() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef><defName>Steel</defName><statBases><MarketValue>1.9</MarketValue><Mass>0.5</Mass></statBases></ThingDef>
+            <ThingDef><defName>Gold</defName><statBases><MarketValue>10</MarketValue></statBases></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName><workAmount>100</workAmount></RecipeDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+
+        let counts = parser.tag_counts(None);
+        assert_eq!(counts[0], ("defName".to_string(), 3));
+        assert!(counts.contains(&("MarketValue".to_string(), 2)));
+        assert!(counts.contains(&("workAmount".to_string(), 1)));
+
+        let things = parser.tag_counts(Some("ThingDef"));
+        assert_eq!(things[..3], [("MarketValue".to_string(), 2), ("defName".to_string(), 2), ("statBases".to_string(), 2)]);
+        assert!(!things.iter().any(|(tag, _)| tag == "workAmount"));
+    }
+
+    #[test]
+    fn stricter_reference_modes_find_fewer_references() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef><defName>TableMachining</defName></ThingDef>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef><defName>Gold</defName></ThingDef>
+            <ThingDef><defName>Plasteel</defName></ThingDef>
+            <ThingDef>
+                <defName>Gun_Revolver</defName>
+                <description>Made from Gold</description>
+                <recipeMaker><recipeUsers><li>TableMachining</li></recipeUsers></recipeMaker>
+                <costList><Steel>30</Steel></costList>
+                <smeltProducts><li>Gold</li></smeltProducts>
+                <modExtensions><li Material="Plasteel"/></modExtensions>
+            </ThingDef>
+        </Defs>"#)]);
+        let references = |mode: ReferenceMode| {
+            let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+            parser.quiet = true;
+            parser.reference_mode = mode;
+            parser.scan_defs_directory().unwrap();
+            let mut references = parser.parsed_defs.iter().find(|def| def.def_name == "Gun_Revolver").unwrap().references_out.clone();
+            references.sort();
+            references
+        };
+
+        assert_eq!(references(ReferenceMode::Strict), ["TableMachining"]);
+        assert_eq!(references(ReferenceMode::Balanced), ["Gold", "Steel", "TableMachining"]);
+        assert_eq!(references(ReferenceMode::Loose), ["Gold", "Plasteel", "Steel", "TableMachining"]);
+    }
+
+    #[test]
+    fn files_without_defs_are_listed() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>"),
+            ("Data/Core/Defs/Empty.xml", "<Defs>\n  <!-- <ThingDef><defName>Gold</defName></ThingDef> -->\n</Defs>"),
+            ("Data/Core/Defs/Patches.xml", "<Patch><Operation Class=\"PatchOperationAdd\"/></Patch>"),
+        ]);
+        let parser = scanned(&dir);
+
+        let mut files: Vec<String> = parser.files_without_defs.iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["Empty.xml", "Patches.xml"]);
+    }
+
+    #[test]
+    fn may_require_entries_of_inactive_mods_are_dropped_when_filtering() {
+        let xml = r#"<Defs><ThingDef>
+            <defName>Steel</defName>
+            <stuffCategories>
+                <li>Metallic</li>
+                <li MayRequire="Ludeon.RimWorld.Biotech">Mechanoid</li>
+                <li MayRequireAnyOf="Ludeon.RimWorld.Royalty, Ludeon.RimWorld.Ideology">Ornate</li>
+            </stuffCategories>
+        </ThingDef></Defs>"#;
+        let items = |parser: &DefParser| -> Vec<String> {
+            let defs = parser.parse_xml_str(xml, Path::new("Data/Core/Defs/Items.xml")).unwrap();
+            defs[0].elements.iter().find(|e| e.name == "stuffCategories").unwrap().children.iter()
+                .filter_map(|li| li.content.clone())
+                .collect()
+        };
+
+        let mut parser = DefParser::new(String::new());
+        assert_eq!(items(&parser), ["Metallic", "Mechanoid", "Ornate"]);
+
+        parser.active_mods = Some(HashSet::from(["ludeon.rimworld".to_string(), "ludeon.rimworld.ideology".to_string()]));
+        assert_eq!(items(&parser), ["Metallic", "Ornate"]);
+
+        parser.active_mods = Some(HashSet::from(["ludeon.rimworld.biotech".to_string()]));
+        assert_eq!(items(&parser), ["Metallic", "Mechanoid"]);
+    }
+
+    #[test]
+    fn field_provenance_names_the_def_each_element_came_from() {
+        let dir = install(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="BaseItem" Abstract="True">
+                <stackLimit>75</stackLimit>
+                <statBases><MarketValue>1</MarketValue></statBases>
+            </ThingDef>
+            <ThingDef ParentName="BaseItem">
+                <defName>Steel</defName>
+                <stackLimit>100</stackLimit>
+            </ThingDef>
+        </Defs>"#)]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.field_provenance = true;
+        parser.scan_defs_directory().unwrap();
+        parser.resolve_inheritance();
+
+        let steel = parser.parsed_defs.iter().find(|def| def.def_name == "Steel").unwrap();
+        let resolved = steel.resolved_elements.as_ref().unwrap();
+        let origin = |name: &str| resolved.iter().find(|element| element.name == name).unwrap().origin.clone();
+        assert_eq!(origin("stackLimit").as_deref(), Some("self"));
+        assert_eq!(origin("defName").as_deref(), Some("self"));
+        assert_eq!(origin("statBases").as_deref(), Some("BaseItem"));
+        let stat_bases = resolved.iter().find(|element| element.name == "statBases").unwrap();
+        assert_eq!(stat_bases.children[0].origin.as_deref(), Some("BaseItem"));
+    }
+
+
+    #[test]
+    fn obtainable_things_are_told_apart_from_internal_ones() {
+        let dir = install(&[("Data/Core/Defs/Things.xml", r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True"><tradeability>All</tradeability></ThingDef>
+            <ThingDef ParentName="ResourceBase"><defName>Steel</defName><deepCommonality>1</deepCommonality></ThingDef>
+            <ThingDef><defName>Gold</defName><tradeability>None</tradeability></ThingDef>
+            <ThingDef><defName>Jade</defName></ThingDef>
+            <ThingDef><defName>MineableJade</defName><building><mineableThing>Jade</mineableThing></building></ThingDef>
+            <ThingDef><defName>Wall</defName><designationCategory>Structure</designationCategory></ThingDef>
+            <ThingDef><defName>Gun_Revolver</defName><recipeMaker/></ThingDef>
+            <ThingDef><defName>Kibble</defName></ThingDef>
+            <RecipeDef><defName>MakeKibble</defName><products><Kibble>50</Kibble></products></RecipeDef>
+            <ThingDef><defName>Pack_Survival</defName></ThingDef>
+            <ScenarioDef><defName>Crashlanded</defName><parts><li><thingDef>Pack_Survival</thingDef></li></parts></ScenarioDef>
+        </Defs>"#)]);
+        let parser = scanned(&dir);
+        let via = |name: &str| parser.parsed_defs.iter().find(|def| def.def_name == name).unwrap().obtainable.clone();
+
+        assert_eq!(via("Steel").unwrap(), ["tradeable", "spawns"]);
+        assert_eq!(via("Jade").unwrap(), ["spawns"]);
+        assert_eq!(via("Wall").unwrap(), ["buildable"]);
+        assert_eq!(via("Gun_Revolver").unwrap(), ["craftable"]);
+        assert_eq!(via("Kibble").unwrap(), ["craftable"]);
+        assert_eq!(via("Pack_Survival").unwrap(), ["scenario"]);
+        assert!(via("Gold").unwrap().is_empty());
+        assert!(via("MineableJade").unwrap().is_empty());
+        assert_eq!(via("ResourceBase"), None);
+        assert_eq!(via("MakeKibble"), None);
+    }
+
+
+    #[test]
+    fn defs_are_attributed_to_their_mods_package_id() {
+        let dir = install(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>"),
+            ("Data/Royalty/Defs/Items.xml", "<Defs><ThingDef><defName>Gold</defName></ThingDef></Defs>"),
+            ("Mods/Guns/About/About.xml", &about("Author.Guns")),
+            ("Mods/Guns/1.5/Defs/Guns.xml", "<Defs><ThingDef><defName>Gun_Laser</defName></ThingDef></Defs>"),
+        ]);
+
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.add_mod_dir(&dir.path().join("Mods/Guns")).unwrap();
+        parser.scan_defs_directory().unwrap();
+
+        let mut defs: Vec<(&str, Option<&str>)> = parser.parsed_defs.iter()
+            .map(|def| (def.def_name.as_str(), def.source_mod.as_deref()))
+            .collect();
+        defs.sort();
+        assert_eq!(defs, [
+            ("Gold", Some("Ludeon.RimWorld.Royalty")),
+            ("Gun_Laser", Some("Author.Guns")),
+            ("Steel", Some("Ludeon.RimWorld")),
+        ]);
+    }
+}
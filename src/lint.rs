@@ -0,0 +1,87 @@
+//! Structural checks for patterns the game tolerates inconsistently but that usually
+//! point at mistakes in the XML (`--lint`).
+
+use crate::{DefElement, RimWorldDef};
+
+/// Describes every structural problem in the def, with the dotted path of the offending element.
+pub fn lint_def(def: &RimWorldDef) -> Vec<String> {
+    let mut findings = Vec::new();
+    let prefix = format!("{} \"{}\" ({})", def.def_type, def.def_name, def.file_path);
+
+    for element in &def.elements {
+        if element.name == "li" {
+            findings.push(format!("{}: <li> directly under the def root", prefix));
+        }
+    }
+
+    lint_recursive(&def.elements, "", &prefix, &mut findings);
+    findings
+}
+
+fn lint_recursive(elements: &[DefElement], parent_path: &str, prefix: &str, findings: &mut Vec<String>) {
+    let has_li = elements.iter().any(|e| e.name == "li");
+    let has_fields = elements.iter().any(|e| e.name != "li");
+    if has_li && has_fields && !parent_path.is_empty() {
+        findings.push(format!("{}: {}: <li> mixed with named fields, the element is not a list", prefix, parent_path));
+    }
+
+    for element in elements {
+        let path = if parent_path.is_empty() {
+            element.name.clone()
+        } else {
+            format!("{}.{}", parent_path, element.name)
+        };
+
+        if element.name == "defName" && !element.children.is_empty() {
+            findings.push(format!("{}: {}: defName has child elements", prefix, path));
+        } else if element.content.is_some() && !element.children.is_empty() {
+            findings.push(format!("{}: {}: text content next to child elements", prefix, path));
+        }
+
+        lint_recursive(&element.children, &path, prefix, findings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+    use std::path::Path;
+
+    /// Findings for one ThingDef with `body` as its elements, without the def prefix.
+    fn lint(body: &str) -> Vec<String> {
+        let xml = format!("<Defs><ThingDef><defName>Steel</defName>{}</ThingDef></Defs>", body);
+        let defs = DefParser::new(String::new()).parse_xml_str(&xml, Path::new("Data/Core/Defs/Items.xml")).unwrap();
+        lint_def(&defs[0]).into_iter()
+            .map(|finding| finding.trim_start_matches("ThingDef \"Steel\" (Data/Core/Defs/Items.xml): ").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn li_under_the_def_root() {
+        assert_eq!(lint("<li>Metallic</li>"), ["<li> directly under the def root"]);
+        assert!(lint("<stuffCategories><li>Metallic</li></stuffCategories>").is_empty());
+    }
+
+    #[test]
+    fn li_mixed_with_named_fields() {
+        assert_eq!(lint("<stuffCategories><li>Metallic</li><label>x</label></stuffCategories>"),
+            ["stuffCategories: <li> mixed with named fields, the element is not a list"]);
+        assert!(lint("<statBases><MarketValue>1</MarketValue><Mass>2</Mass></statBases>").is_empty());
+    }
+
+    #[test]
+    fn def_name_with_child_elements() {
+        let xml = "<Defs><ThingDef><defName>Steel<x>1</x></defName></ThingDef></Defs>";
+        let defs = DefParser::new(String::new()).parse_xml_str(xml, Path::new("Items.xml")).unwrap();
+        assert!(lint_def(&defs[0]).iter().any(|finding| finding.ends_with("defName: defName has child elements")));
+        assert!(lint("").is_empty());
+    }
+
+    #[test]
+    fn text_next_to_child_elements() {
+        assert_eq!(lint("<graphicData>Things/Steel<texPath>x</texPath></graphicData>"),
+            ["graphicData: text content next to child elements"]);
+        assert!(lint("<graphicData><texPath>Things/Steel</texPath></graphicData>").is_empty());
+    }
+}
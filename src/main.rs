@@ -1,612 +1,841 @@
 use anyhow::Result;
 use chrono::Utc;
-use clap::{Arg, Command};
-use quick_xml::events::Event;
-use quick_xml::Reader;
-use serde::{Deserialize, Serialize};
+use clap::{Arg, ArgAction, Command};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use globset::{Glob, GlobSetBuilder};
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
 use serde_json::json;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::Write;
-use std::path::Path;
-use walkdir::WalkDir;
-
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct DefElement {
-    name: String,
-    attributes: HashMap<String, String>,
-    content: Option<String>,
-    children: Vec<DefElement>,
-    depth: usize,
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use rimworld_def_viewer::deprecated::DeprecationTable;
+use rimworld_def_viewer::extension_rules::ExtensionRules;
+use rimworld_def_viewer::{normalize_number, write_output, DefElement, DefParser, Reference, ReferenceMode, RimWorldDef, DEFAULT_RECURSION_LIMIT};
+
+mod csv_export;
+mod dataset;
+mod graph;
+#[cfg(feature = "parquet")]
+mod parquet_export;
+#[cfg(feature = "profile")]
+mod profile;
+mod save;
+#[cfg(feature = "tantivy")]
+mod search_index;
+#[cfg(feature = "sqlite")]
+mod sqlite_export;
+
+use dataset::{Dataset, DatasetDef};
+use graph::Graph;
+use save::SaveModList;
+
+/// Version of the emitted dataset structure, written as `format_version` in every output file.
+/// Bump it whenever a field is removed, renamed or changes meaning; purely additive fields
+/// don't require a bump since frontends ignore keys they don't know.
+///
+/// 2: `def_name` is the defName even when the def also has a `Name` attribute, which moved
+/// to `inherit_name`; free-text content no longer produces references.
+const FORMAT_VERSION: u32 = 2;
+
+/// Def types `--only-referenced` keeps even without incoming references, since they are
+/// browsed directly rather than reached through other defs.
+const DEFAULT_KEEP_TYPES: &[&str] = &[
+    "ThingDef",
+    "TerrainDef",
+    "ResearchProjectDef",
+    "RecipeDef",
+    "PawnKindDef",
+    "FactionDef",
+    "BiomeDef",
+    "ScenarioDef",
+];
+
+const DEFAULT_SUMMARY_LENGTH: usize = 120;
+
+const DEFAULT_GRAPH_DEPTH: usize = 2;
+
+const DEFAULT_OUTPUT: &str = "dataset.json.zstd";
+const DEFAULT_COMPRESSION_LEVEL: i32 = 19;
+const DEFAULT_COMPRESSION_THREADS: u32 = 16;
+
+const DICTIONARY_FILE: &str = "dictionary.zstd-dict";
+const DICTIONARY_SIZE: usize = 112_640;   // zstd's default dictionary size
+const DICTIONARY_SAMPLES: usize = 5_000;
+
+struct DatasetGenerator {
+    defs: Vec<RimWorldDef>,
+    rimworld_path: String,
+    size_report: bool,            // Print per-category JSON sizes before writing
+    flat: bool,                   // Emit a plain array of defs instead of categories
+    merge_same_name: bool,        // One entry per type and defName with all sources as variants
+    normalize_numbers: bool,      // Canonicalize numeric element content in flattened output
+    nested_elements: bool,        // Emit the full element tree instead of the flattened list
+    summary_length: usize,        // Maximum characters of each def's summary line
+    inheritance_cycles: Vec<Vec<String>>, // From the parser, reported in stats
+    dangling_reference_count: usize,      // From the parser, reported in stats
+    split_dir: Option<PathBuf>,   // Write one file per def type into this directory
+    split_size: Option<usize>,    // Write size-bounded chunks instead of one file
+    checksum_manifest: bool,      // Record size and SHA-256 of each split file in index.json
+    dictionary: Option<Vec<u8>>,  // Trained zstd dictionary used for split files and chunks
+    delta: HashMap<(String, String), &'static str>, // (type, name) -> "added"/"modified" vs a base dataset
+    preserve_order: bool,         // Order defs by source position instead of name
+    timestamp: bool,              // Record the generation time in stats and the summary
+    gzip: bool,                   // Also write dataset.json.gz
+    plain_json: bool,             // Also write an uncompressed dataset.json
+    emit_json: bool,              // Also write a pretty-printed dataset.pretty.json
+    output_path: PathBuf,         // The compressed dataset; sidecars are written next to it
+    compression_level: i32,       // zstd level 1-22, 0 writes uncompressed JSON
+    threads: u32,                 // zstd worker threads for the single dataset file
 }
 
-impl DefElement {
-    fn to_xml(&self, indent: usize) -> String {
-        let mut xml = String::new();
-        let indent_str = "  ".repeat(indent);
-        
-        // Opening tag with attributes
-        xml.push_str(&format!("{}<{}", indent_str, self.name));
-        
-        // Add attributes if any
-        if !self.attributes.is_empty() {
-            for (key, value) in &self.attributes {
-                xml.push_str(&format!(" {}=\"{}\"", key, value));
-            }
-        }
-        
-        // Check if this is a self-closing tag (no content and no children)
-        if self.content.is_none() && self.children.is_empty() {
-            xml.push_str(" />\n");
-            return xml;
-        }
-        
-        xml.push('>');
-        
-        // Add content if it exists
-        if let Some(content) = &self.content {
-            if self.children.is_empty() {
-                // Simple content on same line
-                xml.push_str(content);
-            } else {
-                // Content with children - put content on new line
-                xml.push('\n');
-                xml.push_str(&format!("{}{}", "  ".repeat(indent + 1), content));
-                xml.push('\n');
-            }
-        } else if !self.children.is_empty() {
-            xml.push('\n');
-        }
-        
-        // Add children
-        for child in &self.children {
-            xml.push_str(&child.to_xml(indent + 1));
-        }
-        
-        // Closing tag
-        if !self.children.is_empty() || (self.content.is_some() && !self.children.is_empty()) {
-            xml.push_str(&format!("{}</{}>", indent_str, self.name));
-        } else {
-            xml.push_str(&format!("</{}>", self.name));
+impl DatasetGenerator {
+    fn new(defs: Vec<RimWorldDef>, rimworld_path: String, output_path: PathBuf) -> Result<Self> {
+        Ok(Self {
+            defs,
+            rimworld_path,
+            size_report: false,
+            flat: false,
+            merge_same_name: false,
+            normalize_numbers: false,
+            nested_elements: false,
+            summary_length: DEFAULT_SUMMARY_LENGTH,
+            inheritance_cycles: Vec::new(),
+            dangling_reference_count: 0,
+            split_dir: None,
+            split_size: None,
+            checksum_manifest: false,
+            dictionary: None,
+            delta: HashMap::new(),
+            preserve_order: false,
+            timestamp: true,
+            gzip: false,
+            plain_json: false,
+            emit_json: false,
+            output_path,
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            threads: DEFAULT_COMPRESSION_THREADS,
+        })
+    }
+
+    /// The output path without its compression extension (`dataset.json` for
+    /// `dataset.json.zstd`), which the gzip, plain JSON and stats files are named after.
+    /// Other names get `.json` appended so the plain file never replaces the compressed one.
+    fn output_base(&self) -> PathBuf {
+        match self.output_path.extension().and_then(|e| e.to_str()) {
+            Some("zstd") | Some("zst") => self.output_path.with_extension(""),
+            _ => PathBuf::from(format!("{}.json", self.output_path.display())),
         }
-        xml.push('\n');
-        
-        xml
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct RimWorldDef {
-    def_name: String,
-    def_type: String,
-    label: Option<String>,
-    description: Option<String>,
-    parent_name: Option<String>,
-    is_abstract: bool,
-    elements: Vec<DefElement>,
-    file_path: String,
-    tags: Vec<String>,
-    stats: Option<DefStats>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    references_out: Vec<String>,  // DefNames this def references
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    references_in: Vec<String>,   // DefNames that reference this def
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    code_references: Vec<String>, // C# class names referenced (from Class attributes)
-    raw_xml: String,             // Original XML representation
-    extension: String,           // RimWorld extension/DLC: Core, Royalty, Ideology, Biotech, Anomaly
-}
+    /// `name` next to the output file, where the other artifacts of a run go (`chunks/`,
+    /// `defs.sqlite`, ...), so `-o dist/data/dataset.json.zstd` keeps them in `dist/data`.
+    fn artifact_path(&self, name: &str) -> PathBuf {
+        self.output_path.with_file_name(name)
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct DefStats {
-    element_count: usize,
-    max_depth: usize,
-    has_complex_structure: bool,
-}
+    /// Drops every definition for which `keep` returns false. References were already
+    /// computed against the full set, so kept defs still point at removed ones by name.
+    fn retain_defs<F>(&mut self, filter_name: &str, keep: F)
+    where
+        F: Fn(&RimWorldDef) -> bool,
+    {
+        let before = self.defs.len();
+        self.defs.retain(keep);
+        println!("  ✓ {}: {} definitions removed, {} remaining",
+            filter_name,
+            before - self.defs.len(),
+            self.defs.len());
+    }
 
-struct DefParser {
-    rimworld_data_path: String,
-    parsed_defs: Vec<RimWorldDef>,
-    def_name_map: HashMap<String, Vec<usize>>,  // Map def names to their indices in parsed_defs
-}
+    /// Keeps the defs of the listed extensions when `keep_listed`, otherwise drops them.
+    /// Extensions are matched ignoring case.
+    fn retain_extensions<'a>(&mut self, filter_name: &str, extensions: impl IntoIterator<Item = &'a String>, keep_listed: bool) {
+        let extensions: Vec<String> = extensions.into_iter().map(|e| e.trim().to_lowercase()).collect();
+        self.retain_defs(filter_name, |def| extensions.contains(&def.extension.to_lowercase()) == keep_listed);
+    }
 
-impl DefParser {
-    fn new(rimworld_data_path: String) -> Self {
-        Self {
-            rimworld_data_path,
-            parsed_defs: Vec::new(),
-            def_name_map: HashMap::new(),
-        }
+    /// Keeps the defs something refers to, and every def of the `keep_types`.
+    fn retain_referenced(&mut self, keep_types: &[String]) {
+        self.retain_defs("Reference filter", |def| {
+            !def.references_in.is_empty() || keep_types.contains(&def.def_type)
+        });
     }
 
-    fn detect_extension(&self, file_path: &Path) -> String {
-        // Convert path to string for analysis
-        let path_str = file_path.to_string_lossy().to_lowercase();
-        
-        // Check for DLC/extension folders in the path
-        if path_str.contains("anomaly") {
-            "Anomaly".to_string()
-        } else if path_str.contains("biotech") {
-            "Biotech".to_string()
-        } else if path_str.contains("ideology") {
-            "Ideology".to_string()
-        } else if path_str.contains("royalty") {
-            "Royalty".to_string()
-        } else if path_str.contains("odyssey") {
-            "Odyssey".to_string()
-        } else if path_str.contains("core") {
-            "Core".to_string()
-        } else {
-            // Default to Unknown if we can't determine the extension
-            "Unknown".to_string()
+    /// Keeps the defs that contain every tag, or any of them when `match_any`.
+    fn retain_elements(&mut self, tags: &[&String], match_any: bool) {
+        self.retain_defs("Element filter", |def| {
+            if match_any {
+                tags.iter().any(|tag| def.has_element(tag))
+            } else {
+                tags.iter().all(|tag| def.has_element(tag))
+            }
+        });
+    }
+
+    /// Keeps the defs whose defName matches any of the glob patterns.
+    fn retain_name_globs<'a>(&mut self, patterns: impl IntoIterator<Item = &'a String>) -> Result<()> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern)?);
         }
+        let name_globs = builder.build()?;
+        self.retain_defs("Name filter", |def| name_globs.is_match(&def.def_name));
+        Ok(())
     }
 
-    fn parse_xml_file(&mut self, file_path: &Path) -> Result<()> {
-        let content = fs::read_to_string(file_path)?;
-        let mut reader = Reader::from_str(&content);
-        reader.trim_text(true);
+    /// Keeps the defs of the mods active in the save, official content included, and
+    /// reports the save's mods that none of the scanned defs belong to.
+    fn retain_save_mods(&mut self, save: &SaveModList) {
+        let scanned: HashSet<String> = self.defs.iter()
+            .filter_map(|def| def.source_mod.as_deref())
+            .map(str::to_lowercase)
+            .collect();
+        for save_mod in save.mods.iter().filter(|m| !scanned.contains(&m.id.to_lowercase())) {
+            println!("  ⚠ {} is not among the scanned mods, its defs are not included", save_mod.id);
+        }
 
-        let mut buf = Vec::new();
-        let mut element_stack = Vec::new();
-        let mut in_defs = false;
+        let active: HashSet<String> = save.mods.iter().map(|m| m.id.to_lowercase()).collect();
+        self.retain_defs("Save mod filter", |def| {
+            def.source_mod.as_ref().is_some_and(|id| active.contains(&id.to_lowercase()))
+        });
+    }
 
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Start(ref e)) => {
-                    let name = std::str::from_utf8(e.name().as_ref()).unwrap_or("").to_string();
-                    
-                    if name == "Defs" {
-                        in_defs = true;
-                        continue;
-                    }
+    /// Merges `references_in` from a previously generated dataset, so defs keep incoming
+    /// references from referencers that this run didn't parse.
+    fn merge_prior_references(&mut self, prior_path: &Path) -> Result<()> {
+        println!("\nMerging incoming references from {}...", prior_path.display());
 
-                    if in_defs && !element_stack.is_empty() {
-                        let mut attributes = HashMap::new();
-                        for attr in e.attributes().flatten() {
-                            let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("").to_string();
-                            let value = std::str::from_utf8(&attr.value).unwrap_or("").to_string();
-                            attributes.insert(key, value);
-                        }
-
-                        let element = DefElement {
-                            name: name.clone(),
-                            attributes,
-                            content: None,
-                            children: Vec::new(),
-                            depth: element_stack.len(),
-                        };
-
-                        element_stack.push(element);
-                    } else if in_defs {
-                        let mut attributes = HashMap::new();
-                        for attr in e.attributes().flatten() {
-                            let key = std::str::from_utf8(attr.key.as_ref()).unwrap_or("").to_string();
-                            let value = std::str::from_utf8(&attr.value).unwrap_or("").to_string();
-                            attributes.insert(key, value);
-                        }
-
-                        let element = DefElement {
-                            name: name.clone(),
-                            attributes,
-                            content: None,
-                            children: Vec::new(),
-                            depth: 0,
-                        };
-
-                        element_stack.push(element);
-                    }
-                }
-                Ok(Event::End(ref e)) => {
-                    let name = std::str::from_utf8(e.name().as_ref()).unwrap_or("").to_string();
-                    
-                    if name == "Defs" {
-                        in_defs = false;
-                        continue;
-                    }
+        let mut prior_refs: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for def in Dataset::read(prior_path)?.into_defs() {
+            prior_refs.entry(def.key()).or_default().extend(def.references_in);
+        }
 
-                    if in_defs && !element_stack.is_empty() {
-                        let element = element_stack.pop().unwrap();
-                        
-                        if element_stack.is_empty() {
-                            let def_name = element.attributes.get("Name")
-                                .or_else(|| element.children.iter().find(|c| c.name == "defName").and_then(|c| c.content.as_ref()))
-                                .map_or("Unknown", |v| v).to_string();
-                            
-                            let label = element.children.iter().find(|c| c.name == "label").and_then(|c| c.content.as_ref()).cloned();
-                            let description = element.children.iter().find(|c| c.name == "description").and_then(|c| c.content.as_ref()).cloned();
-                            let parent_name = element.attributes.get("ParentName").cloned();
-                            let is_abstract = element.attributes.get("Abstract").map(|v| v == "True").unwrap_or(false);
-                            
-                            let tags = self.generate_tags(&element, is_abstract, parent_name.is_some());
-                            let stats = self.calculate_stats(&element.children);
-
-                            // Generate raw XML
-                            let raw_xml = element.to_xml(0);
-
-                            // Detect extension from file path
-                            let extension = self.detect_extension(file_path);
-
-                            // Make file path relative to RimWorld directory
-                            let relative_path = if let Ok(stripped) = file_path.strip_prefix(&self.rimworld_data_path) {
-                                stripped.to_string_lossy().to_string()
-                            } else {
-                                file_path.file_name().unwrap_or_default().to_string_lossy().to_string()
-                            };
-                            
-                            let rim_def = RimWorldDef {
-                                def_name,
-                                def_type: element.name.clone(),
-                                label,
-                                description,
-                                parent_name,
-                                is_abstract,
-                                elements: element.children.clone(),
-                                file_path: relative_path,
-                                tags,
-                                stats,
-                                references_out: Vec::new(),
-                                references_in: Vec::new(),
-                                code_references: Vec::new(),
-                                raw_xml,
-                                extension,
-                            };
-
-                            self.parsed_defs.push(rim_def);
-                        } else if let Some(parent) = element_stack.last_mut() {
-                            parent.children.push(element);
-                        }
-                    }
-                }
-                Ok(Event::Text(e)) => {
-                    let text = e.unescape().unwrap_or_default().trim().to_string();
-                    if !text.is_empty() && !element_stack.is_empty() {
-                        if let Some(element) = element_stack.last_mut() {
-                            element.content = Some(text);
-                        }
+        let mut merged_count = 0;
+        for def in &mut self.defs {
+            if let Some(refs) = prior_refs.get(&(def.def_type.clone(), def.def_name.clone())) {
+                for ref_name in refs {
+                    if !def.references_in.contains(ref_name) {
+                        def.references_in.push(ref_name.clone());
+                        merged_count += 1;
                     }
                 }
-                Ok(Event::Eof) => break,
-                Err(e) => return Err(anyhow::anyhow!("Error parsing XML: {}", e)),
-                _ => {}
             }
-            buf.clear();
         }
 
+        println!("  ✓ {} incoming references merged from prior dataset", merged_count);
         Ok(())
     }
 
-    fn scan_defs_directory(&mut self) -> Result<()> {
-        let defs_path = Path::new(&self.rimworld_data_path).join("Data");
-        println!("Scanning directory: {}", defs_path.display());
-        
-        let mut file_count = 0;
-        let mut processed_count = 0;
-        let mut error_count = 0;
-        
-        for entry in WalkDir::new(&defs_path) {
-            let entry = entry?;
-            if entry.file_type().is_file() && entry.path().extension().unwrap_or_default() == "xml" {
-                file_count += 1;
-                let initial_def_count = self.parsed_defs.len();
-                
-                match self.parse_xml_file(entry.path()) {
-                    Ok(_) => {
-                        processed_count += 1;
-                        let new_defs = self.parsed_defs.len() - initial_def_count;
-                        if new_defs > 0 {
-                            println!("  ✓ {}: {} definitions", 
-                                entry.path().file_name().unwrap_or_default().to_string_lossy(), 
-                                new_defs);
-                        }
-                    },
-                    Err(e) => {
-                        error_count += 1;
-                        eprintln!("  ✗ Error parsing {}: {}", entry.path().display(), e);
-                    }
-                }
+    /// Keeps only defs that are new or whose XML changed compared to a base dataset
+    /// (e.g. Core + DLC), marking each with `delta: "added"` or `"modified"`.
+    fn apply_delta_base(&mut self, base_path: &Path) -> Result<()> {
+        println!("\nComparing against base dataset {}...", base_path.display());
+
+        let base: HashMap<(String, String), String> = Dataset::read(base_path)?
+            .into_defs()
+            .map(|def| (def.key(), def.raw_xml))
+            .collect();
+
+        for def in &self.defs {
+            let key = (def.def_type.clone(), def.def_name.clone());
+            match base.get(&key) {
+                None => { self.delta.insert(key, "added"); }
+                Some(raw_xml) if *raw_xml != def.raw_xml => { self.delta.insert(key, "modified"); }
+                Some(_) => {}
             }
         }
-        
-        println!("\nScan complete:");
-        println!("  Files found: {}", file_count);
-        println!("  Files processed: {}", processed_count);
-        println!("  Errors: {}", error_count);
-        println!("  Total definitions: {}", self.parsed_defs.len());
-        
-        // Build reference mappings
-        self.build_reference_mappings();
-        
+
+        let added = self.delta.values().filter(|status| **status == "added").count();
+        println!("  ✓ {} added, {} modified", added, self.delta.len() - added);
+
+        let delta = std::mem::take(&mut self.delta);
+        self.retain_defs("Delta filter", |def| delta.contains_key(&(def.def_type.clone(), def.def_name.clone())));
+        self.delta = delta;
+
         Ok(())
     }
-    
-    fn generate_tags(&self, element: &DefElement, is_abstract: bool, has_parent: bool) -> Vec<String> {
-        let mut tags = Vec::new();
-        
-        if is_abstract {
-            tags.push("Abstract".to_string());
+
+    fn read_game_version(&self) -> String {
+        let version_path = Path::new(&self.rimworld_path).join("Version.txt");
+        match fs::read_to_string(version_path) {
+            Ok(content) => content.trim().to_string(),
+            Err(_) => "Unknown".to_string(),
         }
-        
-        if has_parent {
-            tags.push("Inherits".to_string());
+    }
+
+    fn generate_dataset_file(&self) -> Result<()> {
+        if self.size_report {
+            self.print_size_report(&self.build_category_data())?;
         }
-        
-        // Add tags based on common element names
-        let common_elements: Vec<&str> = element.children.iter().map(|e| e.name.as_str()).collect();
-        
-        if common_elements.contains(&"costList") {
-            tags.push("Craftable".to_string());
+
+        if let Some(split_dir) = &self.split_dir {
+            return self.generate_split_files(split_dir);
         }
-        
-        if common_elements.contains(&"researchPrerequisites") {
-            tags.push("Research Required".to_string());
+
+        if let Some(max_bytes) = self.split_size {
+            return self.generate_chunked_files(&self.artifact_path("chunks"), max_bytes);
         }
-        
-        if common_elements.contains(&"statBases") {
-            tags.push("Has Stats".to_string());
+
+        let data = self.build_dataset_value();
+        let json_data = serde_json::to_string(&data)?;
+        println!("      JSON size: {} bytes", json_data.len());
+
+        // Pretty-printed for reading and diffing, from the same value as the dataset file
+        if self.emit_json {
+            let pretty_path = self.output_base().with_extension("pretty.json");
+            let pretty = serde_json::to_string_pretty(&data)?;
+            write_output(&pretty_path, &pretty)?;
+            println!("  ✓ Pretty-printed dataset written: {} ({} bytes)", pretty_path.display(), pretty.len());
         }
-        
-        if common_elements.contains(&"comps") {
-            tags.push("Has Components".to_string());
+
+        // Level 0 skips zstd entirely and writes the JSON as is
+        if self.compression_level == 0 {
+            println!("\nGenerating uncompressed dataset file...");
+            let json_path = self.output_base();
+            write_output(&json_path, &json_data)?;
+            println!("  ✓ Dataset file written: {} ({} bytes, uncompressed)", json_path.display(), json_data.len());
+        } else {
+            println!("\nGenerating compressed dataset file...");
+
+            let compressed_data = self.create_compressed_data(&json_data)?;
+            println!("  ✓ Data compressed: {} bytes", compressed_data.len());
+
+            // Write to static dataset file
+            let dataset_path = &self.output_path;
+            write_output(dataset_path, &compressed_data)?;
+            println!("  ✓ Dataset file written: {} ({} bytes)", dataset_path.display(), compressed_data.len());
         }
-        
-        if common_elements.contains(&"recipes") {
-            tags.push("Has Recipes".to_string());
+
+        // Static hosts like GitHub Pages serve gzip transparently but don't know zstd
+        if self.gzip {
+            let gzip_path = PathBuf::from(format!("{}.gz", self.output_base().display()));
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(json_data.as_bytes())?;
+            let gzip_data = encoder.finish()?;
+            write_output(&gzip_path, &gzip_data)?;
+            println!("  ✓ Gzip dataset written: {} ({} bytes)", gzip_path.display(), gzip_data.len());
+        }
+        if self.plain_json && self.compression_level != 0 {
+            let json_path = self.output_base();
+            write_output(&json_path, &json_data)?;
+            println!("  ✓ Uncompressed dataset written: {} ({} bytes)", json_path.display(), json_data.len());
+        }
+
+        // A flat array has no room for stats, so they go into a sidecar
+        if self.flat {
+            let stats_path = self.output_base().with_extension("stats.json");
+            let stats = json!({
+                "format_version": FORMAT_VERSION,
+                "stats": self.build_stats_json()
+            });
+            write_output(&stats_path, serde_json::to_string_pretty(&stats)?)?;
+            println!("  ✓ Stats sidecar written: {}", stats_path.display());
         }
         
-        tags
+        Ok(())
     }
-    
-    fn calculate_stats(&self, elements: &[DefElement]) -> Option<DefStats> {
-        if elements.is_empty() {
-            return None;
+
+    /// Trains a zstd dictionary on a sample of the serialized defs. Many small files of
+    /// structurally similar defs compress much better when they share one.
+    fn train_dictionary(&mut self) -> Result<()> {
+        println!("\nTraining compression dictionary...");
+
+        let step = (self.defs.len() / DICTIONARY_SAMPLES).max(1);
+        let samples = self.defs.iter()
+            .step_by(step)
+            .map(|def| serde_json::to_string(&self.def_json(def)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let dictionary = zstd::dict::from_samples(&samples, DICTIONARY_SIZE)?;
+        println!("  ✓ Dictionary trained from {} samples ({} bytes)", samples.len(), dictionary.len());
+        self.dictionary = Some(dictionary);
+
+        Ok(())
+    }
+
+    /// Writes the trained dictionary next to the files compressed with it.
+    fn write_dictionary(&self, dir: &Path) -> Result<Option<&'static str>> {
+        let Some(dictionary) = &self.dictionary else {
+            return Ok(None);
+        };
+
+        write_output(dir.join(DICTIONARY_FILE), dictionary)?;
+        println!("  ✓ Dictionary written: {}", dir.join(DICTIONARY_FILE).display());
+        Ok(Some(DICTIONARY_FILE))
+    }
+
+    /// Uncompressed JSON size and def count of every category, largest first.
+    fn print_size_report(&self, category_data: &[serde_json::Value]) -> Result<()> {
+        println!("\nSize report (uncompressed JSON):");
+
+        let sizes = Self::category_sizes(category_data)?;
+        let total: usize = sizes.iter().map(|(_, size, _)| size).sum();
+        for (name, size, count) in sizes {
+            println!("  {:<40} {:>12} bytes {:>5.1}% {:>7} defs",
+                name,
+                size,
+                size as f64 * 100.0 / total.max(1) as f64,
+                count);
         }
-        
-        let element_count = self.count_elements(elements);
-        let max_depth = self.calculate_max_depth(elements, 0);
-        let has_complex_structure = element_count > 20 || max_depth > 4;
-        
-        Some(DefStats {
-            element_count,
-            max_depth,
-            has_complex_structure,
-        })
+        println!("  {:<40} {:>12} bytes", "Total", total);
+
+        Ok(())
     }
-    
-    fn count_elements(&self, elements: &[DefElement]) -> usize {
-        elements.len() + elements.iter().map(|e| self.count_elements(&e.children)).sum::<usize>()
+
+    /// (name, JSON bytes, definition count) per category, largest first.
+    fn category_sizes(category_data: &[serde_json::Value]) -> Result<Vec<(&str, usize, u64)>> {
+        let mut sizes = Vec::new();
+        for category in category_data {
+            let size = serde_json::to_string(category)?.len();
+            sizes.push((category["name"].as_str().unwrap_or_default(), size, category["count"].as_u64().unwrap_or(0)));
+        }
+        sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        Ok(sizes)
     }
-    
-    fn calculate_max_depth(&self, elements: &[DefElement], current_depth: usize) -> usize {
-        elements.iter().map(|e| {
-            if e.children.is_empty() {
-                current_depth + 1
-            } else {
-                self.calculate_max_depth(&e.children, current_depth + 1)
-            }
-        }).max().unwrap_or(current_depth)
+
+    /// Writes one compressed file per def type plus an `index.json` listing them, so a
+    /// frontend only has to fetch the categories it displays.
+    fn generate_split_files(&self, split_dir: &Path) -> Result<()> {
+        println!("\nGenerating split dataset files in {}...", split_dir.display());
+
+        // Categories are compressed in parallel, each with its own single-threaded encoder.
+        // par_iter keeps the input order, so the index is identical to a sequential run.
+        let written: Vec<Result<(serde_json::Value, usize)>> = self.build_category_data()
+            .par_iter()
+            .map(|category| -> Result<(serde_json::Value, usize)> {
+                let name = category["name"].as_str().unwrap_or_default().to_string();
+                let file_name = format!("{}.json.zstd", sanitize_file_name(&name));
+
+                let json_data = serde_json::to_string(&json!({
+                    "format_version": FORMAT_VERSION,
+                    "category": category
+                }))?;
+                let compressed = self.compress_with_workers(&json_data, 0)?;
+                write_output(split_dir.join(&file_name), &compressed)?;
+
+                let mut entry = json!({
+                    "name": name,
+                    "display_name": category["display_name"],
+                    "count": category["count"],
+                    "file": file_name
+                });
+                if self.checksum_manifest {
+                    entry["size"] = json!(compressed.len());
+                    entry["sha256"] = json!(format!("{:x}", Sha256::digest(&compressed)));
+                }
+                Ok((entry, compressed.len()))
+            })
+            .collect();
+
+        let mut index_entries = Vec::new();
+        for result in written {
+            let (entry, size) = result?;
+            println!("  ✓ {} ({} bytes)", entry["file"].as_str().unwrap_or_default(), size);
+            index_entries.push(entry);
+        }
+
+        let mut index = json!({
+            "format_version": FORMAT_VERSION,
+            "categories": index_entries,
+            "stats": self.build_stats_json()
+        });
+        if let Some(dictionary_file) = self.write_dictionary(split_dir)? {
+            index["dictionary"] = json!(dictionary_file);
+        }
+        let index_path = split_dir.join("index.json");
+        write_output(&index_path, serde_json::to_string_pretty(&index)?)?;
+        println!("  ✓ Index written: {}", index_path.display());
+
+        Ok(())
     }
     
-    fn build_reference_mappings(&mut self) {
-        println!("\nBuilding reference mappings...");
-        
-        // First pass: build def name index
-        for (idx, def) in self.parsed_defs.iter().enumerate() {
-            self.def_name_map.entry(def.def_name.clone()).or_default().push(idx);
-        }
-        
-        // Second pass: extract references and build relationships
-        let mut reference_count = 0;
-        for i in 0..self.parsed_defs.len() {
-            let def_name = self.parsed_defs[i].def_name.clone();
-            let (references, code_refs) = self.extract_references(&self.parsed_defs[i].elements);
-            
-            // Filter to only valid def names and exclude self-references
-            let valid_refs: Vec<String> = references.into_iter()
-                .filter(|ref_name| {
-                    self.def_name_map.contains_key(ref_name) && ref_name != &def_name
-                })
-                .collect();
-            
-            reference_count += valid_refs.len();
-            
-            // Update outgoing references
-            self.parsed_defs[i].references_out = valid_refs.clone();
-            
-            // Update code references (C# References)
-            self.parsed_defs[i].code_references = code_refs;
-            
-            // Update incoming references for each referenced def
-            for ref_name in valid_refs {
-                if let Some(ref_indices) = self.def_name_map.get(&ref_name) {
-                    // Add the reference to ALL definitions with this name
-                    for &ref_idx in ref_indices {
-                        self.parsed_defs[ref_idx].references_in.push(def_name.clone());
+    /// Packs definitions, ordered by type then name, into numbered chunks whose compressed
+    /// size stays below `max_bytes`, plus an `index.json` mapping def ranges to chunks.
+    fn generate_chunked_files(&self, chunk_dir: &Path, max_bytes: usize) -> Result<()> {
+        println!("\nGenerating dataset chunks of at most {} bytes in {}...", max_bytes, chunk_dir.display());
+
+        let mut sorted_defs: Vec<&RimWorldDef> = self.defs.iter().collect();
+        sorted_defs.sort_by(|a, b| compare_defs(a, b));
+        let serialized = sorted_defs.iter()
+            .map(|def| serde_json::to_string(&self.def_json(def)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Chunks are sized from the compression ratio of the previous chunk, then
+        // shrunk until the compressed result actually fits.
+        let mut ratio = 0.25;
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < serialized.len() {
+            let budget = (max_bytes as f64 / ratio) as usize;
+            let mut end = start + 1;
+            let mut uncompressed = serialized[start].len();
+            while end < serialized.len() && uncompressed + serialized[end].len() < budget {
+                uncompressed += serialized[end].len() + 1;
+                end += 1;
+            }
+
+            loop {
+                let json_data = format!("{{\"format_version\":{},\"definitions\":[{}]}}",
+                    FORMAT_VERSION,
+                    serialized[start..end].join(","));
+                let compressed = self.compress_with_workers(&json_data, 0)?;
+                ratio = compressed.len() as f64 / json_data.len() as f64;
+
+                let count = end - start;
+                if compressed.len() <= max_bytes || count == 1 {
+                    if compressed.len() > max_bytes {
+                        eprintln!("  ⚠ {} \"{}\" alone compresses to {} bytes, above the chunk size",
+                            sorted_defs[start].def_type, sorted_defs[start].def_name, compressed.len());
                     }
+                    chunks.push((start, end, compressed));
+                    break;
                 }
+
+                let fitting = (count as f64 * max_bytes as f64 / compressed.len() as f64 * 0.95) as usize;
+                end = start + fitting.clamp(1, count - 1);
             }
+            start = end;
         }
-        
-        // Handle parent references
-        for i in 0..self.parsed_defs.len() {
-            if let Some(parent_name) = &self.parsed_defs[i].parent_name.clone() {
-                if let Some(parent_indices) = self.def_name_map.get(parent_name) {
-                    let child_name = self.parsed_defs[i].def_name.clone();
-                    for &parent_idx in parent_indices {
-                        if !self.parsed_defs[parent_idx].references_in.contains(&child_name) {
-                            self.parsed_defs[parent_idx].references_in.push(child_name.clone());
-                        }
-                    }
-                }
+
+        let mut index_entries = Vec::new();
+        for (i, (start, end, compressed)) in chunks.iter().enumerate() {
+            let file_name = format!("chunk-{:04}.json.zstd", i);
+            write_output(chunk_dir.join(&file_name), compressed)?;
+            println!("  ✓ {} ({} definitions, {} bytes)", file_name, end - start, compressed.len());
+
+            let (first, last) = (sorted_defs[*start], sorted_defs[end - 1]);
+            let mut entry = json!({
+                "file": file_name,
+                "count": end - start,
+                "first": { "def_type": first.def_type, "def_name": first.def_name },
+                "last": { "def_type": last.def_type, "def_name": last.def_name }
+            });
+            if self.checksum_manifest {
+                entry["size"] = json!(compressed.len());
+                entry["sha256"] = json!(format!("{:x}", Sha256::digest(compressed)));
             }
+            index_entries.push(entry);
         }
-        
-        println!("  ✓ Reference mappings built: {} references found", reference_count);
+
+        let mut index = json!({
+            "format_version": FORMAT_VERSION,
+            "chunks": index_entries,
+            "stats": self.build_stats_json()
+        });
+        if let Some(dictionary_file) = self.write_dictionary(chunk_dir)? {
+            index["dictionary"] = json!(dictionary_file);
+        }
+        let index_path = chunk_dir.join("index.json");
+        write_output(&index_path, serde_json::to_string_pretty(&index)?)?;
+        println!("  ✓ Index written: {}", index_path.display());
+
+        Ok(())
     }
     
-    fn extract_references(&self, elements: &[DefElement]) -> (Vec<String>, Vec<String>) {
-        let mut references = Vec::new();
-        let mut code_references = Vec::new();
+    /// The whole dataset as written to the dataset file, `--emit-json` pretty-prints the same value.
+    fn build_dataset_value(&self) -> serde_json::Value {
+        println!("    Processing definitions for compression...");
         
-        self.extract_references_recursive(elements, &mut references, &mut code_references);
+        if self.flat {
+            json!(self.build_flat_data())
+        } else {
+            json!({
+                "format_version": FORMAT_VERSION,
+                "categories": self.build_category_data(),
+                "stats": self.build_stats_json()
+            })
+        }
+    }
+
+    fn create_compressed_data(&self, json_data: &str) -> Result<Vec<u8>> {
+        let compressed = self.compress(json_data)?;
         
-        // Deduplicate references
-        references.sort();
-        references.dedup();
-        code_references.sort();
-        code_references.dedup();
+        println!("      Compressed size: {} bytes ({}% reduction, level {})", 
+            compressed.len(), 
+            100 - (compressed.len() * 100 / json_data.len()),
+            self.compression_level);
         
-        (references, code_references)
+        // Return raw compressed bytes
+        Ok(compressed)
     }
-    
-    fn extract_references_recursive(&self, elements: &[DefElement], references: &mut Vec<String>, code_references: &mut Vec<String>) {
-        for element in elements {
-            // Check element name - could be a def reference (like <Muffalo>0.1</Muffalo>)
-            if element.name != "defName" && element.name != "li" {
-                references.push(element.name.clone());
-            }
-            
-            // Check element content - any element could contain a def reference
-            if let Some(content) = &element.content {
-                // Skip if it's the defName element itself
-                if element.name != "defName" {
-                    references.push(content.clone());
-                }
-            }
-            
-            // Check attributes
-            for (key, value) in &element.attributes {
-                if key == "Class" {
-                    // C# class references
-                    code_references.push(value.clone());
-                } else {
-                    // Other attributes might be def references
-                    references.push(value.clone());
-                }
-            }
-            
-            // Recursively check children
-            self.extract_references_recursive(&element.children, references, code_references);
+
+    /// Compact per-category summary for a landing page: display name, count, a few
+    /// sample defNames and the def with the most incoming references.
+    fn write_overview(&self, path: &Path) -> Result<()> {
+        const SAMPLE_COUNT: usize = 5;
+
+        let mut categories: BTreeMap<&str, Vec<&RimWorldDef>> = BTreeMap::new();
+        for def in &self.defs {
+            categories.entry(def.def_type.as_str()).or_default().push(def);
         }
-    }
-}
 
-struct DatasetGenerator {
-    defs: Vec<RimWorldDef>,
-    rimworld_path: String,
-}
+        let mut overview = Vec::new();
+        for (name, mut defs) in categories {
+            defs.sort_by(|a, b| compare_defs(a, b));
+            let samples: Vec<&str> = defs.iter()
+                .filter(|def| !def.is_abstract)
+                .take(SAMPLE_COUNT)
+                .map(|def| def.def_name.as_str())
+                .collect();
+            // Ties go to the alphabetically first name since defs are sorted
+            let top = defs.iter()
+                .filter(|def| !def.references_in.is_empty())
+                .max_by(|a, b| a.references_in.len().cmp(&b.references_in.len()).then_with(|| b.def_name.cmp(&a.def_name)))
+                .map(|def| json!({
+                    "def_name": def.def_name,
+                    "label": def.label,
+                    "references_in": def.references_in.len()
+                }));
 
-impl DatasetGenerator {
-    fn new(defs: Vec<RimWorldDef>, rimworld_path: String) -> Result<Self> {
-        Ok(Self { defs, rimworld_path })
+            overview.push(json!({
+                "name": name,
+                "display_name": Self::format_category_name(name),
+                "count": defs.len(),
+                "samples": samples,
+                "top_referenced": top
+            }));
+        }
+        overview.sort_by(|a, b| a["display_name"].as_str().cmp(&b["display_name"].as_str()));
+
+        let data = json!({
+            "format_version": FORMAT_VERSION,
+            "stats": self.build_stats_json(),
+            "categories": overview
+        });
+        write_output(path, serde_json::to_string_pretty(&data)?)?;
+        println!("  ✓ Overview written: {}", path.display());
+        Ok(())
     }
 
-    fn read_game_version(&self) -> String {
-        let version_path = Path::new(&self.rimworld_path).join("Version.txt");
-        match fs::read_to_string(version_path) {
-            Ok(content) => content.trim().to_string(),
-            Err(_) => "Unknown".to_string(),
+    /// Markdown overview for a mod's README: stats, a table of def types with counts and
+    /// a collapsible list of defNames per type.
+    fn write_summary_markdown(&self, path: &Path) -> Result<()> {
+        let stats = self.get_stats();
+
+        let mut categories: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for def in &self.defs {
+            categories.entry(def.def_type.as_str()).or_default().push(def.def_name.as_str());
         }
-    }
 
-    fn generate_dataset_file(&self) -> Result<()> {
-        println!("\nGenerating compressed dataset file...");
-        
-        // Create compressed data
-        let compressed_data = self.create_compressed_data()?;
-        println!("  ✓ Data compressed: {} bytes", compressed_data.len());
-        
-        // Write to static dataset file
-        let dataset_path = "dataset.json.zstd";
-        fs::write(dataset_path, &compressed_data)?;
-        println!("  ✓ Dataset file written: {} ({} bytes)", dataset_path, compressed_data.len());
-        
+        let mut markdown = String::new();
+        markdown.push_str("# Definitions\n\n");
+        markdown.push_str(&format!("- **Definitions:** {}\n", stats.total_defs));
+        markdown.push_str(&format!("- **Def types:** {}\n", stats.total_categories));
+        markdown.push_str(&format!("- **Files:** {}\n", stats.total_files));
+        markdown.push_str(&format!("- **Game version:** {}\n", stats.game_version));
+        if let Some(generated_at) = &stats.generated_at {
+            markdown.push_str(&format!("- **Generated:** {}\n", generated_at));
+        }
+        markdown.push('\n');
+
+        markdown.push_str("| Def type | Count |\n|---|---:|\n");
+        for (def_type, names) in &categories {
+            markdown.push_str(&format!("| {} | {} |\n", def_type, names.len()));
+        }
+
+        for (def_type, names) in &mut categories {
+            names.sort_unstable();
+            markdown.push_str(&format!("\n<details>\n<summary>{} ({})</summary>\n\n", def_type, names.len()));
+            for name in names.iter() {
+                markdown.push_str(&format!("- `{}`\n", name));
+            }
+            markdown.push_str("\n</details>\n");
+        }
+
+        write_output(path, markdown)?;
+        println!("  ✓ Markdown summary written: {}", path.display());
         Ok(())
     }
-    
-    fn create_compressed_data(&self) -> Result<Vec<u8>> {
-        println!("    Processing definitions for compression...");
-        
+
+    fn build_category_data(&self) -> Vec<serde_json::Value> {
         // Create a simplified data structure for the frontend
-        let mut categories: HashMap<String, Vec<&RimWorldDef>> = HashMap::new();
+        let mut categories: BTreeMap<String, Vec<&RimWorldDef>> = BTreeMap::new();
         for def in &self.defs {
-            categories.entry(def.def_type.clone()).or_insert_with(Vec::new).push(def);
+            categories.entry(def.def_type.clone()).or_default().push(def);
         }
         
         let mut category_data = Vec::new();
         for (name, defs) in categories {
             let mut sorted_defs = defs.clone();
-            sorted_defs.sort_by(|a, b| a.def_name.cmp(&b.def_name));
+            if self.preserve_order {
+                sorted_defs.sort_by_key(|def| def.sequence);
+            } else {
+                sorted_defs.sort_by(|a, b| compare_defs(a, b));
+            }
+            let definitions = self.definitions_json(&sorted_defs);
             
             category_data.push(json!({
                 "name": name,
-                "display_name": self.format_category_name(&name),
-                "count": sorted_defs.len(),
-                "definitions": sorted_defs.iter().map(|def| {
-                    json!({
-                        "def_name": def.def_name,
-                        "def_type": def.def_type,
-                        "label": def.label,
-                        "description": def.description,
-                        "parent_name": def.parent_name,
-                        "is_abstract": def.is_abstract,
-                        "file_path": def.file_path,
-                        "tags": def.tags,
-                        "elements": self.flatten_elements(&def.elements),
-                        "references_out": def.references_out,
-                        "references_in": def.references_in,
-                        "code_references": def.code_references,
-                        "raw_xml": def.raw_xml,
-                        "extension": def.extension
-                    })
-                }).collect::<Vec<_>>()
+                "display_name": Self::format_category_name(&name),
+                "count": definitions.len(),
+                "definitions": definitions
             }));
         }
         
         category_data.sort_by(|a, b| a["display_name"].as_str().cmp(&b["display_name"].as_str()));
-        
-        let stats = self.get_stats();
-        
-        let data = json!({
-            "categories": category_data,
-            "stats": {
-                "total_defs": stats.total_defs,
-                "total_categories": stats.total_categories,
-                "total_files": stats.total_files,
-                "game_version": stats.game_version,
-                "generated_at": stats.generated_at
-            }
-        });
-        
-        let json_data = serde_json::to_string(&data)?;
-        println!("      JSON size: {} bytes", json_data.len());
-        
-        // Compress with zstd using manual encoder with long distance matching
-        let mut encoder = zstd::Encoder::new(Vec::new(), 19)?;
-        encoder.long_distance_matching(true)?;
-        encoder.multithread(16)?;
-        encoder.write_all(json_data.as_bytes())?;
-        let compressed = encoder.finish()?;
-        
-        println!("      Compressed size: {} bytes ({}% reduction)", 
-            compressed.len(), 
-            100 - (compressed.len() * 100 / json_data.len()));
-        
-        // Return raw compressed bytes
-        Ok(compressed)
+        category_data
     }
 
-    
-    fn format_category_name(&self, name: &str) -> String {
-        // Convert camelCase to Title Case
-        let mut result = String::new();
-        let mut prev_lower = false;
-        
-        for (i, ch) in name.chars().enumerate() {
-            if i == 0 {
-                result.push(ch.to_uppercase().next().unwrap());
-            } else if ch.is_uppercase() && prev_lower {
+    /// All definitions as one array ordered by type then name, or in source order with
+    /// `--preserve-order`, for `--flat`.
+    fn build_flat_data(&self) -> Vec<serde_json::Value> {
+        let mut sorted_defs: Vec<&RimWorldDef> = self.defs.iter().collect();
+        if self.preserve_order {
+            sorted_defs.sort_by_key(|def| def.sequence);
+        } else {
+            sorted_defs.sort_by(|a, b| compare_defs(a, b));
+        }
+        self.definitions_json(&sorted_defs)
+    }
+
+    /// Output entries for defs sorted by type and name. With `--merge-same-name`, defs
+    /// sharing type and name become one entry listing every source in `variants`.
+    fn definitions_json(&self, sorted_defs: &[&RimWorldDef]) -> Vec<serde_json::Value> {
+        if !self.merge_same_name {
+            return sorted_defs.iter().map(|def| self.def_json(def)).collect();
+        }
+
+        sorted_defs
+            .chunk_by(|a, b| a.def_type == b.def_type && a.def_name == b.def_name)
+            .map(|group| {
+                let mut merged = self.def_json(group[0]);
+
+                let mut references_out: Vec<&String> = group.iter().flat_map(|def| &def.references_out).collect();
+                references_out.sort();
+                references_out.dedup();
+                let mut references_in: Vec<&String> = group.iter().flat_map(|def| &def.references_in).collect();
+                references_in.sort();
+                references_in.dedup();
+
+                let mut references: Vec<&Reference> = group.iter().flat_map(|def| &def.references).collect();
+                references.sort();
+                references.dedup();
+
+                merged["references_out"] = json!(references_out);
+                merged["references"] = json!(references);
+                merged["references_in"] = json!(references_in);
+                merged["variants"] = json!(group.iter().map(|def| json!({
+                    "file_path": def.file_path,
+                    "extension": def.extension,
+                    "raw_xml": def.raw_xml
+                })).collect::<Vec<_>>());
+                merged
+            })
+            .collect()
+    }
+
+    fn def_json(&self, def: &RimWorldDef) -> serde_json::Value {
+        let mut def_json = json!({
+            "def_name": def.def_name,
+            "def_type": def.def_type,
+            "label": def.label,
+            "description": def.description,
+            "inherit_name": def.inherit_name,
+            "parent_name": def.parent_name,
+            "is_abstract": def.is_abstract,
+            "file_path": def.file_path,
+            "file_modified": def.file_modified,
+            "source_line": def.source_line,
+            "sequence": def.sequence,
+            "summary": def_summary(def, self.summary_length),
+            "thing_set_makers": def.thing_set_makers,
+            "tags": def.tags,
+            "elements": if self.nested_elements {
+                json!(def.elements)
+            } else {
+                json!(self.flatten_elements(&def.elements))
+            },
+            "references_out": def.references_out,
+            "references": def.references,
+            "references_in": def.references_in,
+            "code_references": def.code_references,
+            "raw_xml": def.raw_xml,
+            "extension": def.extension,
+            "source_mod": def.source_mod,
+            "mod_name": def.mod_name
+        });
+
+        // Inheriting defs carry their merged tree with --resolve-inheritance, their own
+        // `elements` alone are often nearly empty
+        if let Some(resolved) = &def.resolved_elements {
+            def_json["resolved_elements"] = if self.nested_elements {
+                json!(resolved)
+            } else {
+                json!(self.flatten_elements(resolved))
+            };
+        }
+
+        if let Some(status) = self.delta.get(&(def.def_type.clone(), def.def_name.clone())) {
+            def_json["delta"] = json!(status);
+        }
+
+        if let Some(via) = &def.obtainable {
+            def_json["obtainable"] = json!(!via.is_empty());
+            def_json["obtainable_via"] = json!(via);
+        }
+
+        // Structured fields are only emitted for defs that have them
+        if let serde_json::Value::Object(fields) = json!(def.structured) {
+            for (key, value) in fields {
+                def_json[key] = value;
+            }
+        }
+
+        def_json
+    }
+
+    fn build_stats_json(&self) -> serde_json::Value {
+        let stats = self.get_stats();
+        
+        let mut stats_json = json!({
+            "total_defs": stats.total_defs,
+            "total_categories": stats.total_categories,
+            "total_files": stats.total_files,
+            "game_version": stats.game_version,
+            "inheritance_cycles": stats.inheritance_cycles,
+            "dangling_references": stats.dangling_references
+        });
+        if let Some(generated_at) = stats.generated_at {
+            stats_json["generated_at"] = json!(generated_at);
+        }
+        stats_json
+    }
+
+    fn compress(&self, json_data: &str) -> Result<Vec<u8>> {
+        self.compress_with_workers(json_data, self.threads)
+    }
+
+    /// `workers` of 0 compresses on the calling thread.
+    fn compress_with_workers(&self, json_data: &str, workers: u32) -> Result<Vec<u8>> {
+        // Compress with zstd using manual encoder with long distance matching
+        let mut encoder = match &self.dictionary {
+            Some(dictionary) => zstd::Encoder::with_dictionary(Vec::new(), self.compression_level, dictionary)?,
+            None => zstd::Encoder::new(Vec::new(), self.compression_level)?,
+        };
+        encoder.long_distance_matching(true)?;
+        if workers > 0 {
+            encoder.multithread(workers)?;
+        }
+        encoder.write_all(json_data.as_bytes())?;
+        Ok(encoder.finish()?)
+    }
+
+    
+    fn format_category_name(name: &str) -> String {
+        // Convert camelCase to Title Case
+        let mut result = String::new();
+        let mut prev_lower = false;
+        
+        for (i, ch) in name.chars().enumerate() {
+            if i == 0 {
+                result.push(ch.to_uppercase().next().unwrap());
+            } else if ch.is_uppercase() && prev_lower {
                 result.push(' ');
                 result.push(ch);
             } else {
@@ -644,13 +873,38 @@ impl DatasetGenerator {
                 .join(" ");
         }
         
-        result.push(json!({
+        let normalized = element.content.as_deref()
+            .filter(|_| self.normalize_numbers)
+            .and_then(normalize_number);
+
+        let mut entry = json!({
             "name": element.name,
-            "content": element.content,
+            "content": normalized.as_ref().or(element.content.as_ref()),
             "depth": depth * 20,
             "attributes": attributes_str,
             "has_children": !element.children.is_empty()
-        }));
+        });
+
+        // Keep the source spelling next to the canonical number
+        if normalized.is_some() {
+            entry["raw_content"] = json!(element.content);
+        }
+
+        if let Some(origin) = &element.origin {
+            entry["origin"] = json!(origin);
+        }
+
+        // Key/value lists are emitted as a single map instead of their li/key/value nodes
+        if let Some(entries) = element.key_value_map() {
+            let map: serde_json::Map<String, serde_json::Value> = entries.into_iter()
+                .map(|(key, value)| (key, json!(value)))
+                .collect();
+            entry["map"] = serde_json::Value::Object(map);
+            result.push(entry);
+            return;
+        }
+
+        result.push(entry);
         
         for child in element.children.iter().take(5) {
             self.flatten_element_recursive(child, result, depth + 1);
@@ -667,7 +921,7 @@ impl DatasetGenerator {
         }
 
         let game_version = self.read_game_version();
-        let generated_at = Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        let generated_at = self.timestamp.then(|| Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string());
 
         Stats {
             total_defs: self.defs.len(),
@@ -675,33 +929,745 @@ impl DatasetGenerator {
             total_files: files.len(),
             game_version,
             generated_at,
+            inheritance_cycles: self.inheritance_cycles.clone(),
+            dangling_references: self.dangling_reference_count,
         }
     }
 }
 
+/// One-line summary for list views: the label (or defName) and the first sentence of the
+/// description, cut to `max_chars` characters with an ellipsis.
+fn def_summary(def: &RimWorldDef, max_chars: usize) -> String {
+    let label = def.label.as_deref().unwrap_or(&def.def_name);
+    let first_sentence = def.description.as_deref()
+        .map(|description| {
+            let description = description.trim();
+            let end = description.char_indices()
+                .find(|&(i, c)| {
+                    c == '\n' || (matches!(c, '.' | '!' | '?')
+                        && description[i + c.len_utf8()..].chars().next().is_none_or(char::is_whitespace))
+                })
+                .map(|(i, c)| if c == '\n' { i } else { i + c.len_utf8() })
+                .unwrap_or(description.len());
+            description[..end].trim()
+        })
+        .filter(|sentence| !sentence.is_empty());
+
+    let summary = match first_sentence {
+        Some(sentence) => format!("{} — {}", label, sentence),
+        None => label.to_string(),
+    };
+
+    if summary.chars().count() <= max_chars {
+        return summary;
+    }
+    let truncated: String = summary.chars().take(max_chars.saturating_sub(1)).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+/// Output order of defs: by type and defName, with defs sharing both (e.g. from different
+/// mods) ordered by extension, file path and finally their XML, so the order never depends
+/// on parse order.
+fn compare_defs(a: &RimWorldDef, b: &RimWorldDef) -> std::cmp::Ordering {
+    a.def_type.cmp(&b.def_type)
+        .then_with(|| a.def_name.cmp(&b.def_name))
+        .then_with(|| a.extension.cmp(&b.extension))
+        .then_with(|| a.file_path.cmp(&b.file_path))
+        .then_with(|| a.raw_xml.cmp(&b.raw_xml))
+}
+
+/// Creates an output file for streaming writers, see `write_output`.
+fn create_output_file(path: &Path) -> Result<fs::File> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::File::create(path)?)
+}
+
+/// Turns a def name or type into a single safe path component: separators and other
+/// unusual characters become `_` and leading dots are dropped, so `../x` can't escape
+/// the output directory.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') { c } else { '_' })
+        .collect();
+    let sanitized = sanitized.trim_start_matches('.');
+    if sanitized.is_empty() {
+        "_".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 struct Stats {
     total_defs: usize,
     total_categories: usize,
     total_files: usize,
     game_version: String,
-    generated_at: String,
+    generated_at: Option<String>,
+    inheritance_cycles: Vec<Vec<String>>,
+    dangling_references: usize,
+}
+
+/// The reference graph for `--emit-graph` and `--format dot`, narrowed by `--graph-type`
+/// and `--graph-root`/`--graph-depth`.
+fn project_graph<'a>(defs: &'a [RimWorldDef], matches: &clap::ArgMatches) -> Result<Graph<'a>> {
+    let graph = Graph::project(defs, matches.get_one::<String>("graph-type").map(|t| t.as_str()));
+    match matches.get_one::<String>("graph-root") {
+        Some(root) => {
+            let depth = matches.get_one::<usize>("graph-depth").copied().unwrap_or(DEFAULT_GRAPH_DEPTH);
+            graph.neighborhood(root, depth)
+                .ok_or_else(|| anyhow::anyhow!("--graph-root {} is not a definition in the graph", root))
+        }
+        None => Ok(graph),
+    }
+}
+
+fn debug_file(file_path: &Path, verbose: bool) -> Result<()> {
+    println!("\nParsing {}", file_path.display());
+
+    let mut parser = DefParser::new(String::new());
+    parser.verbose = verbose;
+    let defs = parser.parse_xml_file(file_path)?;
+
+    println!("  ✓ {} definitions parsed\n", defs.len());
+
+    for def in &defs {
+        println!("{}", debug_outline(def));
+    }
+
+    Ok(())
+}
+
+/// The def's header and element tree, one indented line per element, for `debug-file`.
+fn debug_outline(def: &RimWorldDef) -> String {
+    let mut outline = format!("{} \"{}\"\n", def.def_type, def.def_name);
+    if let Some(parent_name) = &def.parent_name {
+        outline.push_str(&format!("  ParentName: {}\n", parent_name));
+    }
+    if def.is_abstract {
+        outline.push_str("  Abstract: true\n");
+    }
+    for element in &def.elements {
+        outline.push_str(&element.to_outline(1));
+    }
+    outline
+}
+
+/// Prints every definition as one JSON line as soon as its file is parsed. Nothing
+/// else is written to stdout so the output can be piped; errors go to stderr.
+fn stream_defs(rimworld_path: &str) -> Result<()> {
+    let parser = DefParser::new(rimworld_path.to_string());
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
+    for def in parser.stream_defs() {
+        match def {
+            Ok(def) => writeln!(out, "{}", serde_json::to_string(&def)?)?,
+            Err(e) => eprintln!("  ✗ {}", e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a single Defs document from stdin and prints its defs as JSON lines. Without
+/// other files there is nothing to map references against.
+fn parse_stdin() -> Result<()> {
+    let mut content = String::new();
+    std::io::stdin().read_to_string(&mut content)?;
+
+    write_defs_ndjson(&content, &mut std::io::stdout().lock())
+}
+
+/// Parses one Defs document and writes each def as a line of JSON.
+fn write_defs_ndjson(content: &str, out: &mut impl Write) -> Result<()> {
+    let parser = DefParser::new(String::new());
+    let defs = parser.parse_xml_str(content, Path::new("<stdin>"))?;
+
+    for def in &defs {
+        writeln!(out, "{}", serde_json::to_string(def)?)?;
+    }
+
+    Ok(())
+}
+
+/// Prints every def whose XML matches `pattern` with the matching lines, or with
+/// `files_only` just the files containing such defs.
+/// The trimmed lines of the def's XML matching `regex`, or None when the XML doesn't match.
+/// A match spanning lines yields no lines.
+fn grep_def<'a>(def: &'a RimWorldDef, regex: &Regex) -> Option<Vec<&'a str>> {
+    if !regex.is_match(&def.raw_xml) {
+        return None;
+    }
+    Some(def.raw_xml.lines().filter(|line| regex.is_match(line)).map(str::trim).collect())
+}
+
+fn grep_defs(rimworld_path: &str, pattern: &str, ignore_case: bool, files_only: bool) -> Result<()> {
+    let regex = RegexBuilder::new(pattern).case_insensitive(ignore_case).build()?;
+    let parser = DefParser::new(rimworld_path.to_string());
+
+    let mut matched_defs = 0;
+    let mut matched_files: Vec<String> = Vec::new();
+    for def in parser.stream_defs() {
+        let def = match def {
+            Ok(def) => def,
+            Err(e) => {
+                eprintln!("  ✗ {}", e);
+                continue;
+            }
+        };
+        let Some(lines) = grep_def(&def, &regex) else {
+            continue;
+        };
+
+        matched_defs += 1;
+        if files_only {
+            // Defs arrive file by file, so repeats are always adjacent
+            if matched_files.last() != Some(&def.file_path) {
+                println!("{}", def.file_path);
+                matched_files.push(def.file_path);
+            }
+            continue;
+        }
+
+        println!("{} {} ({}:{})", def.def_type, def.def_name, def.file_path, def.source_line);
+        for line in lines {
+            println!("    {}", line);
+        }
+    }
+
+    eprintln!("{} matching definitions", matched_defs);
+    Ok(())
+}
+
+/// Re-emits a generated single-file dataset in another format without re-parsing the install.
+fn convert_dataset(input: &Path, output: &Path, format: &str) -> Result<()> {
+    let dataset = Dataset::read(input)?;
+    let def_count = dataset.defs().count();
+
+    // The table exports work on parsed defs, which the dataset entries convert back into
+    if matches!(format, "csv" | "sqlite") {
+        let defs = dataset.defs().map(DatasetDef::to_def).collect::<Result<Vec<_>>>()?;
+        if format == "csv" {
+            csv_export::write_csv(&defs, output)?;
+        } else {
+            #[cfg(feature = "sqlite")]
+            sqlite_export::write_sqlite(&defs, output)?;
+            #[cfg(not(feature = "sqlite"))]
+            return Err(anyhow::anyhow!("--to sqlite requires building with --features sqlite"));
+        }
+        return Ok(());
+    }
+
+    let data = match format {
+        "json" => serde_json::to_vec_pretty(&dataset)?,
+        "ndjson" => {
+            let mut lines = Vec::new();
+            for def in dataset.defs() {
+                serde_json::to_writer(&mut lines, def)?;
+                lines.push(b'\n');
+            }
+            lines
+        }
+        "zstd" => zstd::encode_all(serde_json::to_vec(&dataset)?.as_slice(), 19)?,
+        "gzip" => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            serde_json::to_writer(&mut encoder, &dataset)?;
+            encoder.finish()?
+        }
+        _ => return Err(anyhow::anyhow!("Unsupported output format: {}", format)),
+    };
+
+    write_output(output, &data)?;
+    println!("  ✓ {} definitions converted to {}: {} ({} bytes)", def_count, format, output.display(), data.len());
+    Ok(())
+}
+
+/// Looks up definitions by defName and prints their XML, tags and references, or a single
+/// field's inheritance-resolved value with `--field`. With `json` the matches are printed
+/// as a JSON array of their dataset entries and nothing else goes to stdout.
+/// The value at `pointer` in a def's dataset JSON, strings unquoted and anything else
+/// pretty-printed, or "(not found)" for a path that doesn't exist.
+fn json_pointer_value(def_json: &serde_json::Value, pointer: &str) -> Result<String> {
+    Ok(match def_json.pointer(pointer) {
+        Some(serde_json::Value::String(value)) => value.clone(),
+        Some(value) => serde_json::to_string_pretty(value)?,
+        None => "(not found)".to_string(),
+    })
+}
+
+fn query_def(rimworld_path: &str, def_name: &str, field: Option<&String>, json_pointer: Option<&String>, json: bool) -> Result<()> {
+    let mut parser = DefParser::new(rimworld_path.to_string());
+    parser.quiet = json;
+    parser.scan_defs_directory()?;
+    parser.resolve_inheritance();
+
+    let matches: Vec<RimWorldDef> = parser.parsed_defs.into_iter()
+        .filter(|def| def.def_name == def_name)
+        .collect();
+    if matches.is_empty() {
+        return Err(anyhow::anyhow!("No definition named {}", def_name));
+    }
+
+    // JSON pointers address the def exactly as it is written to the dataset
+    let generator = DatasetGenerator::new(matches, rimworld_path.to_string(), PathBuf::from(DEFAULT_OUTPUT))?;
+
+    if json {
+        let defs: Vec<serde_json::Value> = generator.defs.iter().map(|def| generator.def_json(def)).collect();
+        println!("{}", serde_json::to_string_pretty(&defs)?);
+        return Ok(());
+    }
+
+    println!();
+    if generator.defs.len() > 1 {
+        println!("{} definitions named {}:\n", generator.defs.len(), def_name);
+    }
+    let list = |names: &[String]| if names.is_empty() { "(none)".to_string() } else { names.join(", ") };
+    for def in &generator.defs {
+        match (field, json_pointer) {
+            (Some(field), _) => println!("{} {} ({}): {}",
+                def.def_type,
+                def.def_name,
+                field,
+                def.effective_field(field).unwrap_or("(not set)")),
+            (None, Some(pointer)) => println!("{} {} ({}): {}",
+                def.def_type,
+                def.def_name,
+                pointer,
+                json_pointer_value(&generator.def_json(def), pointer)?),
+            (None, None) => {
+                println!("{} {} ({}:{})", def.def_type, def.def_name, def.file_path, def.source_line);
+                println!("  Tags: {}", list(&def.tags));
+                println!("  References out: {}", list(&def.references_out));
+                println!("  References in: {}", list(&def.references_in));
+                println!("{}\n", def.raw_xml);
+            }
+        }
+    }
+
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    println!("RimWorld XML Documentation Generator");
-    println!("====================================");
-    
     let matches = Command::new("rimworld-xml")
         .about("Generate compressed HTML documentation for RimWorld XML definitions")
+        .subcommand_negates_reqs(true)
+        .subcommand(Command::new("debug-file")
+            .about("Parse a single XML file and print the resulting element trees")
+            .arg(Arg::new("file")
+                .value_name("FILE")
+                .help("XML file to parse")
+                .required(true))
+            .arg(Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::SetTrue)
+                .help("Trace root detection and <Defs> transitions")))
+        .subcommand(Command::new("query")
+            .about("Look up definitions by defName")
+            .arg(Arg::new("rimworld-path")
+                .short('p')
+                .long("path")
+                .value_name("PATH")
+                .help("Path to RimWorld base installation directory")
+                .required(true))
+            .arg(Arg::new("def-name")
+                .long("def-name")
+                .value_name("NAME")
+                .help("defName to look up")
+                .required(true))
+            .arg(Arg::new("field")
+                .long("field")
+                .value_name("PATH")
+                .help("Print only this field's inheritance-resolved value, e.g. statBases/MarketValue"))
+            .arg(Arg::new("json-pointer")
+                .long("json-pointer")
+                .value_name("POINTER")
+                .conflicts_with("field")
+                .help("Print only the value at this JSON Pointer into the def's dataset JSON, e.g. /elements/0/content"))
+            .arg(Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["field", "json-pointer"])
+                .help("Print the matching definitions as a JSON array of their dataset entries, without progress output")))
+        .subcommand(Command::new("convert")
+            .about("Re-emit a generated dataset in another format without re-parsing the install")
+            .arg(Arg::new("input")
+                .value_name("DATASET")
+                .help("Single-file dataset to read (.json.zstd, .json.gz or .json, grouped or flat)")
+                .required(true))
+            .arg(Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("File to write")
+                .required(true))
+            .arg(Arg::new("to")
+                .long("to")
+                .value_name("FORMAT")
+                .value_parser(["json", "ndjson", "zstd", "gzip", "csv", "sqlite"])
+                .required(true)
+                .help("Output format")))
+        .subcommand(Command::new("grep")
+            .about("Print definitions whose XML matches a regular expression")
+            .arg(Arg::new("rimworld-path")
+                .short('p')
+                .long("path")
+                .value_name("PATH")
+                .help("Path to RimWorld base installation directory")
+                .required(true))
+            .arg(Arg::new("pattern")
+                .value_name("REGEX")
+                .help("Regular expression matched against each definition's XML")
+                .required(true))
+            .arg(Arg::new("ignore-case")
+                .short('i')
+                .long("ignore-case")
+                .action(ArgAction::SetTrue)
+                .help("Match case-insensitively"))
+            .arg(Arg::new("files")
+                .long("files")
+                .action(ArgAction::SetTrue)
+                .help("Only list the files containing matching definitions")))
+        .subcommand(Command::new("stream")
+            .about("Print each definition as a JSON line while parsing, without reference mapping")
+            .arg(Arg::new("rimworld-path")
+                .short('p')
+                .long("path")
+                .value_name("PATH")
+                .help("Path to RimWorld base installation directory")
+                .required(true)))
         .arg(Arg::new("rimworld-path")
             .short('p')
             .long("path")
             .value_name("PATH")
             .help("Path to RimWorld base installation directory")
-            .required(true))
+            .required_unless_present("stdin"))
+        .arg(Arg::new("compression-level")
+            .long("compression-level")
+            .value_name("LEVEL")
+            .value_parser(clap::value_parser!(i32).range(0..=22))
+            .help("zstd compression level, 1-22 (default 19); 0 writes uncompressed JSON named .json"))
+        .arg(Arg::new("threads")
+            .long("threads")
+            .value_name("N")
+            .value_parser(clap::value_parser!(u32).range(1..))
+            .help("zstd worker threads for the dataset file (default 16)"))
+        .arg(Arg::new("output")
+            .short('o')
+            .long("output")
+            .value_name("FILE")
+            .help("Where to write the compressed dataset (default dataset.json.zstd); missing directories are created"))
+        .arg(Arg::new("stdin")
+            .long("stdin")
+            .action(ArgAction::SetTrue)
+            .help("Parse one Defs document from standard input and print its definitions as JSON lines"))
+        .arg(Arg::new("only-extension")
+            .long("only-extension")
+            .value_name("EXTENSIONS")
+            .value_delimiter(',')
+            .help("Only output definitions from these extensions (comma-separated, e.g. Core,Biotech)"))
+        .arg(Arg::new("exclude-extension")
+            .long("exclude-extension")
+            .value_name("EXTENSIONS")
+            .value_delimiter(',')
+            .help("Omit definitions from these extensions (comma-separated, e.g. Unknown)"))
+        .arg(Arg::new("extension-rules")
+            .long("extension-rules")
+            .value_name("RULES")
+            .help("TOML file of path rules assigning files to extensions, replacing the built-in DLC rules"))
+        .arg(Arg::new("mod-dir")
+            .long("mod-dir")
+            .value_name("PATH")
+            .action(ArgAction::Append)
+            .help("Also scan this mod folder's Defs, Common/Defs and newest <version>/Defs (repeatable)"))
+        .arg(Arg::new("mods-config")
+            .long("mods-config")
+            .value_name("FILE")
+            .help("ModsConfig.xml whose active mods decide which MayRequire content is kept"))
+        .arg(Arg::new("cache")
+            .long("cache")
+            .value_name("FILE")
+            .help("Reuse the parsed defs of unchanged files from this cache and update it"))
+        .arg(Arg::new("only-referenced")
+            .long("only-referenced")
+            .action(ArgAction::SetTrue)
+            .help("Omit definitions nothing references, unless their type is in --keep-types"))
+        .arg(Arg::new("keep-types")
+            .long("keep-types")
+            .value_name("DEF_TYPES")
+            .value_delimiter(',')
+            .requires("only-referenced")
+            .help("Def types --only-referenced always keeps (comma-separated, default ThingDef,TerrainDef,ResearchProjectDef,...)"))
+        .arg(Arg::new("from-save")
+            .long("from-save")
+            .value_name("SAVE")
+            .help("Scan the mods active in this save file (.rws) in its load order, and only output their definitions and the official content"))
+        .arg(Arg::new("mods-root")
+            .long("mods-root")
+            .value_name("PATH")
+            .action(ArgAction::Append)
+            .requires("from-save")
+            .help("Folder of mod folders searched for the mods in --from-save, besides <RimWorld>/Mods (repeatable)"))
+        .arg(Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .value_parser(["zstd", "parquet", "sqlite", "csv", "dot"])
+            .default_value("zstd")
+            .help("Write the zstd JSON dataset, defs.parquet and references.parquet (parquet feature), defs.sqlite (sqlite feature), a defs.csv index or the reference graph as defs.dot"))
+        .arg(Arg::new("gzip")
+            .long("gzip")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["split-by-type", "split-size"])
+            .help("Also write the dataset as dataset.json.gz for hosts that only serve gzip"))
+        .arg(Arg::new("emit-json")
+            .long("emit-json")
+            .action(ArgAction::SetTrue)
+            .help("Also write the dataset pretty-printed as dataset.pretty.json, for inspecting and diffing"))
+        .arg(Arg::new("plain-json")
+            .long("plain-json")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["split-by-type", "split-size"])
+            .help("Also write the dataset uncompressed as dataset.json"))
+        .arg(Arg::new("flat")
+            .long("flat")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["split-by-type", "split-size"])
+            .help("Emit a flat array of definitions with stats in a dataset.stats.json sidecar"))
+        .arg(Arg::new("preserve-order")
+            .long("preserve-order")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["merge-same-name", "split-size"])
+            .help("Order definitions as they appear in the source files instead of alphabetically"))
+        .arg(Arg::new("no-timestamp")
+            .long("no-timestamp")
+            .action(ArgAction::SetTrue)
+            .help("Leave the generation time out of the stats and the summary so repeated runs write identical files"))
+        .arg(Arg::new("merge-same-name")
+            .long("merge-same-name")
+            .action(ArgAction::SetTrue)
+            .help("Collapse definitions sharing type and defName into one entry with a variants array"))
+        .arg(Arg::new("size-report")
+            .long("size-report")
+            .action(ArgAction::SetTrue)
+            .help("Print each category's uncompressed JSON size and def count, largest first"))
+        .arg(Arg::new("summary-length")
+            .long("summary-length")
+            .value_name("CHARS")
+            .value_parser(clap::value_parser!(usize))
+            .help("Maximum length of each definition's summary line (default 120)"))
+        .arg(Arg::new("nested-elements")
+            .long("nested-elements")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("normalize-numbers")
+            .help("Emit elements as a nested tree (name, attributes, content, children) instead of a flat list"))
+        .arg(Arg::new("normalize-numbers")
+            .long("normalize-numbers")
+            .action(ArgAction::SetTrue)
+            .help("Canonicalize numeric content in flattened elements, keeping the original as raw_content"))
+        .arg(Arg::new("reference-mode")
+            .long("reference-mode")
+            .value_name("MODE")
+            .value_parser(["strict", "balanced", "loose"])
+            .default_value("loose")
+            .help("Which candidates count as references: known fields only, plus exact defName contents, or everything"))
+        .arg(Arg::new("recursion-limit")
+            .long("recursion-limit")
+            .value_name("DEPTH")
+            .value_parser(clap::value_parser!(usize))
+            .help("Reject files whose definitions nest elements deeper than this (default 512)"))
+        .arg(Arg::new("include-source-context")
+            .long("include-source-context")
+            .value_name("LINES")
+            .value_parser(clap::value_parser!(usize))
+            .help("Store this many source lines before and after each def as source_context"))
+        .arg(Arg::new("name-glob")
+            .long("name-glob")
+            .value_name("PATTERN")
+            .action(ArgAction::Append)
+            .help("Only output definitions whose defName matches this glob (repeatable, e.g. \"Gun_*\")"))
+        .arg(Arg::new("has-element")
+            .long("has-element")
+            .value_name("TAG")
+            .action(ArgAction::Append)
+            .help("Only output definitions containing this element anywhere in their tree (repeatable, e.g. comps)"))
+        .arg(Arg::new("has-element-mode")
+            .long("has-element-mode")
+            .value_name("MODE")
+            .value_parser(["all", "any"])
+            .requires("has-element")
+            .help("Whether definitions need every --has-element tag or any of them (default all)"))
+        .arg(Arg::new("count-only")
+            .long("count-only")
+            .action(ArgAction::SetTrue)
+            .help("Only count definitions per type, without building element trees or writing a dataset"))
+        .arg(Arg::new("list-files-with-no-defs")
+            .long("list-files-with-no-defs")
+            .action(ArgAction::SetTrue)
+            .help("List scanned XML files that yielded no definitions (wrong root, empty, all commented out)"))
+        .arg(Arg::new("resolve-inheritance")
+            .long("resolve-inheritance")
+            .action(ArgAction::SetTrue)
+            .help("Merge each definition's ParentName chain into a resolved element tree, emitted as resolved_elements"))
+        .arg(Arg::new("field-provenance")
+            .long("field-provenance")
+            .action(ArgAction::SetTrue)
+            .requires("resolve-inheritance")
+            .help("Annotate resolved elements with the definition each one came from (\"self\" or an ancestor)"))
+        .arg(Arg::new("list-tags")
+            .long("list-tags")
+            .value_name("DEF_TYPE")
+            .num_args(0..=1)
+            .default_missing_value("")
+            .help("Print every element tag with its occurrence count, optionally for one def type, and exit"))
+        .arg(Arg::new("lint")
+            .long("lint")
+            .action(ArgAction::SetTrue)
+            .help("Warn about suspicious XML structure (misplaced li, mixed content, nested defName)"))
+        .arg(Arg::new("check-assets")
+            .long("check-assets")
+            .action(ArgAction::SetTrue)
+            .help("Warn about texture and sound paths that don't exist in any content root's Textures/ or Sounds/"))
+        .arg(Arg::new("check-round-trip")
+            .long("check-round-trip")
+            .action(ArgAction::SetTrue)
+            .help("Reparse every generated raw_xml and warn where it differs from the source tree"))
+        .arg(Arg::new("check-deprecated")
+            .long("check-deprecated")
+            .value_name("TABLE")
+            .num_args(0..=1)
+            .default_missing_value("")
+            .help("Warn about deprecated tags, using the built-in table or a custom TOML table"))
+        .arg(Arg::new("dangling-report")
+            .long("dangling-report")
+            .value_name("FILE")
+            .num_args(0..=1)
+            .default_missing_value("dangling.txt")
+            .help("Write references to undefined definitions (typos, missing DLC) to FILE, default dangling.txt"))
+        .arg(Arg::new("warnings-log")
+            .long("warnings-log")
+            .value_name("FILE")
+            .help("Write all warnings (unresolved parents, ...) to FILE, one per line"))
+        .arg(Arg::new("emit-tantivy")
+            .long("emit-tantivy")
+            .value_name("DIR")
+            .help("Also build a Tantivy full-text index of the output definitions (requires the tantivy feature)"))
+        .arg(Arg::new("summary-markdown")
+            .long("summary-markdown")
+            .value_name("FILE")
+            .help("Also write a Markdown summary (stats, def type table, defNames per type) for a README"))
+        .arg(Arg::new("emit-graph")
+            .long("emit-graph")
+            .value_name("FILE")
+            .help("Also write the reference graph as JSON nodes and edges"))
+        .arg(Arg::new("graph-type")
+            .long("graph-type")
+            .value_name("DEF_TYPE")
+            .help("Only include definitions of this type and the references among them in the graph"))
+        .arg(Arg::new("graph-root")
+            .long("graph-root")
+            .value_name("DEF_NAME")
+            .help("Only include the definitions connected to this one in the graph, see --graph-depth"))
+        .arg(Arg::new("graph-depth")
+            .long("graph-depth")
+            .value_name("HOPS")
+            .value_parser(clap::value_parser!(usize))
+            .requires("graph-root")
+            .help("How many references away from --graph-root definitions may be (default 2)"))
+        .arg(Arg::new("emit-overview")
+            .long("emit-overview")
+            .value_name("FILE")
+            .help("Also write a compact per-category summary (counts, samples, most referenced def) as JSON"))
+        .arg(Arg::new("profile")
+            .long("profile")
+            .value_name("SVG")
+            .help("Sample the run and write a flamegraph to SVG (requires the profile feature)"))
+        .arg(Arg::new("delta-base")
+            .long("delta-base")
+            .value_name("FILE")
+            .help("Only output definitions that are new or changed compared to this base dataset"))
+        .arg(Arg::new("prior-dataset")
+            .long("prior-dataset")
+            .value_name("FILE")
+            .help("Previously generated dataset whose incoming references are merged into this run"))
+        .arg(Arg::new("split-by-type")
+            .long("split-by-type")
+            .visible_alias("split-by-category")
+            .value_name("DIR")
+            .num_args(0..=1)
+            .help("Write one compressed file per def type plus an index.json into DIR (default: defs next to --output)"))
+        .arg(Arg::new("split-size")
+            .long("split-size")
+            .value_name("BYTES")
+            .value_parser(clap::value_parser!(usize))
+            .conflicts_with("split-by-type")
+            .help("Write numbered chunks of at most BYTES compressed plus an index.json into chunks/"))
+        .arg(Arg::new("train-dictionary")
+            .long("train-dictionary")
+            .action(ArgAction::SetTrue)
+            .help("Compress split files or chunks with a shared, trained zstd dictionary written alongside them"))
+        .arg(Arg::new("checksum-manifest")
+            .long("checksum-manifest")
+            .action(ArgAction::SetTrue)
+            .help("Record the size and SHA-256 of every split file or chunk in index.json"))
         .get_matches();
 
+    match matches.subcommand() {
+        Some(("query", sub_matches)) => {
+            return query_def(
+                sub_matches.get_one::<String>("rimworld-path").unwrap(),
+                sub_matches.get_one::<String>("def-name").unwrap(),
+                sub_matches.get_one::<String>("field"),
+                sub_matches.get_one::<String>("json-pointer"),
+                sub_matches.get_flag("json"));
+        }
+        Some(("convert", sub_matches)) => {
+            return convert_dataset(
+                Path::new(sub_matches.get_one::<String>("input").unwrap()),
+                Path::new(sub_matches.get_one::<String>("output").unwrap()),
+                sub_matches.get_one::<String>("to").unwrap());
+        }
+        Some(("grep", sub_matches)) => {
+            return grep_defs(
+                sub_matches.get_one::<String>("rimworld-path").unwrap(),
+                sub_matches.get_one::<String>("pattern").unwrap(),
+                sub_matches.get_flag("ignore-case"),
+                sub_matches.get_flag("files"));
+        }
+        Some(("stream", sub_matches)) => {
+            return stream_defs(sub_matches.get_one::<String>("rimworld-path").unwrap());
+        }
+        Some(("debug-file", sub_matches)) => {
+            let file = sub_matches.get_one::<String>("file").unwrap();
+            return debug_file(Path::new(file), sub_matches.get_flag("verbose"));
+        }
+        _ => {}
+    }
+
+    // Stdout carries the defs, so this runs before anything else is printed
+    if matches.get_flag("stdin") {
+        return parse_stdin();
+    }
+
+    println!("RimWorld XML Documentation Generator");
+    println!("====================================");
+
+    #[cfg(feature = "profile")]
+    let profiler = match matches.get_one::<String>("profile") {
+        Some(_) => Some(profile::start()?),
+        None => None,
+    };
+    #[cfg(not(feature = "profile"))]
+    if let Some(profile_path) = matches.get_one::<String>("profile") {
+        return Err(anyhow::anyhow!("--profile {} requires building with --features profile", profile_path));
+    }
+    #[cfg(not(feature = "tantivy"))]
+    if let Some(index_dir) = matches.get_one::<String>("emit-tantivy") {
+        return Err(anyhow::anyhow!("--emit-tantivy {} requires building with --features tantivy", index_dir));
+    }
+
     let rimworld_path = matches.get_one::<String>("rimworld-path").unwrap();
 
     println!("\nConfiguration:");
@@ -720,14 +1686,1004 @@ fn main() -> Result<()> {
     println!("  ✓ Paths validated");
     
     let mut parser = DefParser::new(rimworld_path.clone());
+
+    for mod_dir in matches.get_many::<String>("mod-dir").into_iter().flatten() {
+        parser.add_mod_dir(Path::new(mod_dir))?;
+    }
+    if let Some(mods_config) = matches.get_one::<String>("mods-config") {
+        let active_mods = save::read_active_mods(Path::new(mods_config))?;
+        println!("  ✓ {} active mods read from {}", active_mods.len(), mods_config);
+        parser.active_mods = Some(active_mods.into_iter().map(|id| id.to_lowercase()).collect());
+    }
+    let save = match matches.get_one::<String>("from-save") {
+        Some(save_path) => {
+            let save = SaveModList::read(Path::new(save_path))?;
+            println!("\nMods in {} (game version {}):", save_path, save.game_version.as_deref().unwrap_or("unknown"));
+            for (position, save_mod) in save.mods.iter().enumerate() {
+                println!("  {:>3}. {} ({})", position + 1, save_mod.name.as_deref().unwrap_or("?"), save_mod.id);
+            }
+            let mut mod_roots = vec![Path::new(rimworld_path).join("Mods")];
+            mod_roots.extend(matches.get_many::<String>("mods-root").into_iter().flatten().map(PathBuf::from));
+            let package_ids: Vec<String> = save.mods.iter().map(|m| m.id.clone()).collect();
+            parser.add_mods_in_load_order(&package_ids, &mod_roots)?;
+            if parser.active_mods.is_none() {
+                parser.active_mods = Some(package_ids.iter().map(|id| id.to_lowercase()).collect());
+            }
+            Some(save)
+        }
+        None => None,
+    };
+
+    if matches.get_flag("count-only") {
+        println!("\nDefinitions per type:");
+        let counts = parser.count_defs()?;
+        for (def_type, count) in &counts {
+            println!("  {:<40} {:>6}", def_type, count);
+        }
+        println!("  {:<40} {:>6}", "Total", counts.values().sum::<usize>());
+        return Ok(());
+    }
+    parser.recursion_limit = matches.get_one::<usize>("recursion-limit").copied().unwrap_or(DEFAULT_RECURSION_LIMIT);
+    parser.source_context_lines = matches.get_one::<usize>("include-source-context").copied().unwrap_or(0);
+    if let Some(rules_path) = matches.get_one::<String>("extension-rules") {
+        parser.extension_rules = ExtensionRules::load(Some(Path::new(rules_path)))?;
+        println!("  ✓ Extension rules loaded from {}", rules_path);
+    }
+    parser.reference_mode = match matches.get_one::<String>("reference-mode").map(|mode| mode.as_str()) {
+        Some("strict") => ReferenceMode::Strict,
+        Some("balanced") => ReferenceMode::Balanced,
+        _ => ReferenceMode::Loose,
+    };
+    if let Some(cache_path) = matches.get_one::<String>("cache") {
+        parser.enable_cache(Path::new(cache_path));
+    }
     parser.scan_defs_directory()?;
+
+    if matches.get_flag("list-files-with-no-defs") {
+        println!("\nFiles without definitions ({}):", parser.files_without_defs.len());
+        for path in &parser.files_without_defs {
+            println!("  {}", path.display());
+        }
+    }
+
+    if let Some(def_type) = matches.get_one::<String>("list-tags") {
+        let def_type = Some(def_type.as_str()).filter(|t| !t.is_empty());
+        println!("\nElement tags{}:", def_type.map(|t| format!(" in {}", t)).unwrap_or_default());
+        for (tag, count) in parser.tag_counts(def_type) {
+            println!("  {:>8}  {}", count, tag);
+        }
+        return Ok(());
+    }
+
+    if matches.get_flag("resolve-inheritance") {
+        parser.field_provenance = matches.get_flag("field-provenance");
+        parser.resolve_inheritance();
+    }
+
+    if matches.get_flag("lint") {
+        parser.lint_structure();
+    }
+
+    if matches.get_flag("check-assets") {
+        parser.check_assets();
+    }
+
+    if matches.get_flag("check-round-trip") {
+        parser.check_round_trip();
+    }
+
+    if let Some(table_path) = matches.get_one::<String>("check-deprecated") {
+        let table_path = Some(Path::new(table_path)).filter(|p| !p.as_os_str().is_empty());
+        let table = DeprecationTable::load(table_path)?;
+        parser.check_deprecated_tags(&table);
+    }
+
+    if let Some(report_path) = matches.get_one::<String>("dangling-report") {
+        let mut report: String = parser.dangling_references.iter()
+            .map(|(source, missing)| format!("{} -> {}\n", source, missing))
+            .collect();
+        if report.is_empty() {
+            report.push_str("No references to undefined definitions\n");
+        }
+        write_output(report_path, report)?;
+        println!("  ✓ {} dangling references written to {}", parser.dangling_references.len(), report_path);
+    }
+
+    if let Some(log_path) = matches.get_one::<String>("warnings-log") {
+        let mut log = parser.warnings.join("\n");
+        log.push('\n');
+        write_output(log_path, log)?;
+        println!("  ✓ {} warnings written to {}", parser.warnings.len(), log_path);
+    }
     
     println!("\nCreating HTML generator...");
-    let generator = DatasetGenerator::new(parser.parsed_defs, rimworld_path.clone())?;
+    let output_path = PathBuf::from(matches.get_one::<String>("output").map(String::as_str).unwrap_or(DEFAULT_OUTPUT));
+    let mut generator = DatasetGenerator::new(parser.parsed_defs, rimworld_path.clone(), output_path)?;
+    generator.inheritance_cycles = parser.inheritance_cycles;
+    generator.dangling_reference_count = parser.dangling_references.len();
     println!("  ✓ Generator initialized");
 
-    generator.generate_dataset_file()?;
+    generator.size_report = matches.get_flag("size-report");
+    generator.flat = matches.get_flag("flat");
+    generator.merge_same_name = matches.get_flag("merge-same-name");
+    generator.normalize_numbers = matches.get_flag("normalize-numbers");
+    generator.nested_elements = matches.get_flag("nested-elements");
+    generator.summary_length = matches.get_one::<usize>("summary-length").copied().unwrap_or(DEFAULT_SUMMARY_LENGTH);
+    generator.preserve_order = matches.get_flag("preserve-order");
+    generator.timestamp = !matches.get_flag("no-timestamp");
+    generator.gzip = matches.get_flag("gzip");
+    generator.plain_json = matches.get_flag("plain-json");
+    generator.emit_json = matches.get_flag("emit-json");
+    generator.compression_level = matches.get_one::<i32>("compression-level").copied().unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+    generator.threads = matches.get_one::<u32>("threads").copied().unwrap_or(DEFAULT_COMPRESSION_THREADS);
+    if generator.compression_level == 0 && (matches.contains_id("split-by-type") || matches.contains_id("split-size")) {
+        return Err(anyhow::anyhow!("--compression-level 0 only applies to the single dataset file, not --split-by-type or --split-size"));
+    }
+    if matches.contains_id("split-by-type") {
+        generator.split_dir = Some(match matches.get_one::<String>("split-by-type") {
+            Some(split_dir) => PathBuf::from(split_dir),
+            None => generator.artifact_path("defs"),
+        });
+    }
+    generator.split_size = matches.get_one::<usize>("split-size").copied();
+    generator.checksum_manifest = matches.get_flag("checksum-manifest");
+
+    if let Some(prior_path) = matches.get_one::<String>("prior-dataset") {
+        generator.merge_prior_references(Path::new(prior_path))?;
+    }
+
+    if let Some(base_path) = matches.get_one::<String>("delta-base") {
+        generator.apply_delta_base(Path::new(base_path))?;
+    }
+
+    // Extension filters run after reference mapping so cross-extension references stay intact
+    if let Some(save) = &save {
+        generator.retain_save_mods(save);
+    }
+    if let Some(only) = matches.get_many::<String>("only-extension") {
+        generator.retain_extensions("Extension filter (only)", only, true);
+    }
+    if let Some(exclude) = matches.get_many::<String>("exclude-extension") {
+        generator.retain_extensions("Extension filter (exclude)", exclude, false);
+    }
+    if matches.get_flag("only-referenced") {
+        let keep_types: Vec<String> = match matches.get_many::<String>("keep-types") {
+            Some(types) => types.map(|t| t.trim().to_string()).collect(),
+            None => DEFAULT_KEEP_TYPES.iter().map(|t| t.to_string()).collect(),
+        };
+        generator.retain_referenced(&keep_types);
+    }
+    if let Some(patterns) = matches.get_many::<String>("name-glob") {
+        generator.retain_name_globs(patterns)?;
+    }
+    if let Some(tags) = matches.get_many::<String>("has-element") {
+        let tags: Vec<&String> = tags.collect();
+        let match_any = matches.get_one::<String>("has-element-mode").is_some_and(|mode| mode == "any");
+        generator.retain_elements(&tags, match_any);
+    }
+
+    if matches.get_flag("train-dictionary") {
+        if generator.split_dir.is_none() && generator.split_size.is_none() {
+            return Err(anyhow::anyhow!("--train-dictionary requires --split-by-type or --split-size"));
+        }
+        generator.train_dictionary()?;
+    }
+
+    match matches.get_one::<String>("format").map(|format| format.as_str()) {
+        Some("parquet") => {
+            #[cfg(feature = "parquet")]
+            parquet_export::write_parquet(&generator.defs, &generator.artifact_path("."))?;
+            #[cfg(not(feature = "parquet"))]
+            return Err(anyhow::anyhow!("--format parquet requires building with --features parquet"));
+        }
+        Some("sqlite") => {
+            #[cfg(feature = "sqlite")]
+            sqlite_export::write_sqlite(&generator.defs, &generator.artifact_path("defs.sqlite"))?;
+            #[cfg(not(feature = "sqlite"))]
+            return Err(anyhow::anyhow!("--format sqlite requires building with --features sqlite"));
+        }
+        Some("csv") => csv_export::write_csv(&generator.defs, &generator.artifact_path("defs.csv"))?,
+        Some("dot") => {
+            let graph = project_graph(&generator.defs, &matches)?;
+            let dot_path = generator.artifact_path("defs.dot");
+            write_output(&dot_path, graph.to_dot())?;
+            println!("  ✓ Reference graph written: {} ({} nodes, {} edges)", dot_path.display(), graph.nodes.len(), graph.edges.len());
+        }
+        _ => generator.generate_dataset_file()?,
+    }
+
+    if let Some(graph_path) = matches.get_one::<String>("emit-graph") {
+        let graph = project_graph(&generator.defs, &matches)?;
+        write_output(graph_path, serde_json::to_string(&graph.to_json())?)?;
+        println!("  ✓ Reference graph written: {} ({} nodes, {} edges)", graph_path, graph.nodes.len(), graph.edges.len());
+    }
+
+    if let Some(summary_path) = matches.get_one::<String>("summary-markdown") {
+        generator.write_summary_markdown(Path::new(summary_path))?;
+    }
+
+    if let Some(overview_path) = matches.get_one::<String>("emit-overview") {
+        generator.write_overview(Path::new(overview_path))?;
+    }
+
+    #[cfg(feature = "tantivy")]
+    if let Some(index_dir) = matches.get_one::<String>("emit-tantivy") {
+        search_index::write_index(&generator.defs, Path::new(index_dir))?;
+    }
+
+    #[cfg(feature = "profile")]
+    if let (Some(profiler), Some(profile_path)) = (&profiler, matches.get_one::<String>("profile")) {
+        profile::write_flamegraph(profiler, Path::new(profile_path))?;
+    }
     
     println!("\n✓ Documentation generation complete!");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFS: &str = r#"<Defs>
+        <ThingDef>
+            <defName>Steel</defName>
+            <label>steel</label>
+        </ThingDef>
+    </Defs>"#;
+
+    /// Defs of one inline Defs document, as if read from `Data/Core/Defs/Test.xml`.
+    fn parse(xml: &str) -> Vec<RimWorldDef> {
+        DefParser::new(String::new()).parse_xml_str(xml, Path::new("Data/Core/Defs/Test.xml")).unwrap()
+    }
+
+    /// Parses an install made of the given files (paths relative to the RimWorld folder)
+    /// with references mapped. The install lives as long as the returned directory.
+    fn scan(files: &[(&str, &str)]) -> (tempfile::TempDir, Vec<RimWorldDef>) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.scan_defs_directory().unwrap();
+        (dir, parser.parsed_defs)
+    }
+
+    /// A generator writing to `dataset.json.zstd` in `dir`, with cheap compression.
+    fn generator(defs: Vec<RimWorldDef>, dir: &Path) -> DatasetGenerator {
+        let mut generator = DatasetGenerator::new(defs, String::new(), dir.join("dataset.json.zstd")).unwrap();
+        generator.compression_level = 1;
+        generator.threads = 0;
+        generator
+    }
+
+    #[test]
+    fn every_dataset_file_carries_the_format_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = generator(parse(DEFS), dir.path());
+        assert_eq!(generator.build_dataset_value()["format_version"], FORMAT_VERSION);
+
+        generator.flat = true;
+        generator.generate_dataset_file().unwrap();
+        let stats: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.path().join("dataset.stats.json")).unwrap()).unwrap();
+        assert_eq!(stats["format_version"], FORMAT_VERSION);
+    }
+
+    #[test]
+    fn convert_to_csv_matches_a_direct_export() {
+        let (dir, defs) = scan(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True">
+                <label>resource</label>
+            </ThingDef>
+            <ThingDef ParentName="ResourceBase">
+                <defName>Steel</defName>
+                <label>steel, "refined"</label>
+            </ThingDef>
+            <RecipeDef>
+                <defName>Make_Component</defName>
+                <costList><Steel>10</Steel></costList>
+            </RecipeDef>
+        </Defs>"#)]);
+
+        csv_export::write_csv(&defs, &dir.path().join("direct.csv")).unwrap();
+        generator(defs, dir.path()).generate_dataset_file().unwrap();
+        convert_dataset(&dir.path().join("dataset.json.zstd"), &dir.path().join("converted.csv"), "csv").unwrap();
+
+        let direct = fs::read_to_string(dir.path().join("direct.csv")).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("converted.csv")).unwrap(), direct);
+        assert!(direct.contains("Steel,ThingDef,Core,\"steel, \"\"refined\"\"\",ResourceBase,false"));
+    }
+
+    #[test]
+    fn from_save_keeps_mod_defs_active_in_the_save() {
+        let mut defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef><defName>Gun_Laser</defName></ThingDef>
+            <ThingDef><defName>Gun_Plasma</defName></ThingDef>
+        </Defs>"#);
+        for (def, source_mod) in defs.iter_mut().zip(["Ludeon.RimWorld", "Author.LaserGuns", "Author.PlasmaGuns"]) {
+            def.source_mod = Some(source_mod.to_string());
+        }
+        let save = SaveModList {
+            game_version: None,
+            mods: ["ludeon.rimworld", "author.laserguns", "author.missing"].iter()
+                .map(|id| save::SaveMod { id: id.to_string(), name: None })
+                .collect(),
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = generator(defs, dir.path());
+        generator.retain_save_mods(&save);
+        let kept: Vec<&str> = generator.defs.iter().map(|def| def.def_name.as_str()).collect();
+        assert_eq!(kept, ["Steel", "Gun_Laser"]);
+    }
+
+    #[test]
+    fn save_header_lists_mods_in_load_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let save_path = dir.path().join("Colony.rws");
+        fs::write(&save_path, r#"<?xml version="1.0" encoding="utf-8"?>
+<savegame>
+    <meta>
+        <gameVersion>1.5.4104 rev435</gameVersion>
+        <modIds><li>ludeon.rimworld</li><li>author.second</li><li>author.first</li></modIds>
+        <modNames><li>Core</li><li>Second</li><li>First</li></modNames>
+    </meta>
+    <game><tickManager><ticksGame>1</ticksGame></tickManager></game>
+</savegame>"#).unwrap();
+
+        let save = SaveModList::read(&save_path).unwrap();
+        assert_eq!(save.game_version.as_deref(), Some("1.5.4104 rev435"));
+        let mods: Vec<(&str, Option<&str>)> = save.mods.iter().map(|m| (m.id.as_str(), m.name.as_deref())).collect();
+        assert_eq!(mods, [("ludeon.rimworld", Some("Core")), ("author.second", Some("Second")), ("author.first", Some("First"))]);
+    }
+
+    #[test]
+    fn chunks_are_written_next_to_the_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = generator(parse(DEFS), dir.path());
+        generator.output_path = dir.path().join("dist").join("dataset.json.zstd");
+        generator.split_size = Some(1 << 20);
+        generator.generate_dataset_file().unwrap();
+
+        assert!(dir.path().join("dist").join("chunks").join("index.json").is_file());
+        assert!(!Path::new("chunks").exists());
+    }
+
+    #[cfg(feature = "tantivy")]
+    #[test]
+    fn search_index_is_replaced_on_rerun() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_dir = dir.path().join("search");
+        search_index::write_index(&parse(DEFS), &index_dir).unwrap();
+        search_index::write_index(&parse(DEFS), &index_dir).unwrap();
+
+        let index = tantivy::Index::open_in_dir(&index_dir).unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        assert_eq!(searcher.num_docs(), parse(DEFS).len() as u64);
+    }
+
+    #[cfg(feature = "tantivy")]
+    #[test]
+    fn search_index_answers_queries() {
+        use tantivy::collector::TopDocs;
+        use tantivy::query::QueryParser;
+        use tantivy::schema::Value;
+
+        let dir = tempfile::tempdir().unwrap();
+        let index_dir = dir.path().join("search");
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Gun_Revolver</defName><label>revolver</label></ThingDef>
+            <ThingDef><defName>Steel</defName><label>steel</label></ThingDef>
+            <RecipeDef><defName>Make_Revolver</defName><label>make revolver</label></RecipeDef>
+        </Defs>"#);
+        search_index::write_index(&defs, &index_dir).unwrap();
+
+        let index = tantivy::Index::open_in_dir(&index_dir).unwrap();
+        let schema = index.schema();
+        let field = |name: &str| schema.get_field(name).unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let parser = QueryParser::for_index(&index, vec![field("def_name"), field("label"), field("description")]);
+        let search = |query: &str| -> Vec<String> {
+            let mut names: Vec<String> = searcher.search(&parser.parse_query(query).unwrap(), &TopDocs::with_limit(10)).unwrap()
+                .into_iter()
+                .map(|(_, address)| {
+                    let doc: tantivy::TantivyDocument = searcher.doc(address).unwrap();
+                    doc.get_first(field("def_name")).and_then(|value| value.as_str()).unwrap().to_string()
+                })
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(search("revolver"), ["Gun_Revolver", "Make_Revolver"]);
+        assert_eq!(search("def_type:ThingDef"), ["Gun_Revolver", "Steel"]);
+        assert_eq!(search("revolver AND def_type:ThingDef"), ["Gun_Revolver"]);
+    }
+
+    #[test]
+    fn extension_filters_ignore_case() {
+        let (dir, defs) = scan(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>"),
+            ("Data/Biotech/Defs/Genes.xml", "<Defs><GeneDef><defName>Hair_Gray</defName></GeneDef></Defs>"),
+            ("Data/Royalty/Defs/Titles.xml", "<Defs><RoyalTitleDef><defName>Knight</defName></RoyalTitleDef></Defs>"),
+        ]);
+
+        let mut only = generator(defs.clone(), dir.path());
+        only.retain_extensions("Extension filter (only)", &["biotech".to_string()], true);
+        let kept: Vec<&str> = only.defs.iter().map(|def| def.def_name.as_str()).collect();
+        assert_eq!(kept, ["Hair_Gray"]);
+
+        let mut exclude = generator(defs, dir.path());
+        exclude.retain_extensions("Extension filter (exclude)", &[" BIOTECH".to_string(), "royalty".to_string()], false);
+        let kept: Vec<&str> = exclude.defs.iter().map(|def| def.def_name.as_str()).collect();
+        assert_eq!(kept, ["Steel"]);
+    }
+
+    #[test]
+    fn debug_outline_lists_every_node() {
+        let defs = parse(r#"<Defs>
+            <ThingDef ParentName="ResourceBase">
+                <defName>Steel</defName>
+                <statBases><MarketValue>1.9</MarketValue></statBases>
+            </ThingDef>
+        </Defs>"#);
+        let outline = debug_outline(&defs[0]);
+
+        assert!(outline.starts_with("ThingDef \"Steel\"\n  ParentName: ResourceBase\n"));
+        for node in ["defName", "statBases", "MarketValue", "1.9"] {
+            assert!(outline.contains(node), "{} missing from\n{}", node, outline);
+        }
+    }
+
+    #[test]
+    fn key_value_lists_are_flattened_to_a_map() {
+        let defs = parse(r#"<Defs>
+            <ThingDef>
+                <defName>Steel</defName>
+                <stuffAdjustments>
+                    <li><key>Beauty</key><value>1.2</value></li>
+                    <li><key>Flammability</key><value>0.4</value></li>
+                </stuffAdjustments>
+            </ThingDef>
+        </Defs>"#);
+        let dir = tempfile::tempdir().unwrap();
+        let flattened = generator(Vec::new(), dir.path()).flatten_elements(&defs[0].elements);
+
+        let map = flattened.iter().find(|entry| entry["name"] == "stuffAdjustments").unwrap();
+        assert_eq!(map["map"], json!({"Beauty": "1.2", "Flammability": "0.4"}));
+        assert!(!flattened.iter().any(|entry| entry["name"] == "key" || entry["name"] == "li"));
+    }
+
+    #[test]
+    fn prior_dataset_keeps_references_from_defs_not_parsed() {
+        let steel = ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName></ThingDef></Defs>");
+        let recipe = ("Data/Core/Defs/Recipes.xml",
+            "<Defs><RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef></Defs>");
+        let (dir, defs) = scan(&[steel, recipe]);
+        generator(defs, dir.path()).generate_dataset_file().unwrap();
+
+        // A run over Steel's file alone doesn't see the recipe referencing it
+        let (_partial_dir, defs) = scan(&[steel]);
+        assert!(defs[0].references_in.is_empty());
+        let mut partial = generator(defs, dir.path());
+        partial.merge_prior_references(&dir.path().join("dataset.json.zstd")).unwrap();
+        assert_eq!(partial.defs[0].references_in, ["Make_Steel"]);
+    }
+
+    #[test]
+    fn checksum_manifest_matches_the_written_files() {
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName></RecipeDef>
+        </Defs>"#);
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = generator(defs, dir.path());
+        let split_dir = dir.path().join("defs");
+        generator.split_dir = Some(split_dir.clone());
+        generator.checksum_manifest = true;
+        generator.generate_dataset_file().unwrap();
+
+        let index: serde_json::Value = serde_json::from_str(&fs::read_to_string(split_dir.join("index.json")).unwrap()).unwrap();
+        let categories = index["categories"].as_array().unwrap();
+        assert_eq!(categories.len(), 2);
+        for category in categories {
+            let content = fs::read(split_dir.join(category["file"].as_str().unwrap())).unwrap();
+            assert_eq!(category["size"], content.len());
+            assert_eq!(category["sha256"], format!("{:x}", Sha256::digest(&content)));
+        }
+    }
+
+    #[test]
+    fn name_globs_keep_matching_defs() {
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Gun_Revolver</defName></ThingDef>
+            <ThingDef><defName>Gun_BoltActionRifle</defName></ThingDef>
+            <ThingDef><defName>MeleeWeapon_Knife</defName></ThingDef>
+            <ThingDef><defName>Steel</defName></ThingDef>
+        </Defs>"#);
+        let dir = tempfile::tempdir().unwrap();
+        let mut generator = generator(defs, dir.path());
+        generator.retain_name_globs(&["Gun_*".to_string(), "*_Kni?e".to_string()]).unwrap();
+
+        let kept: Vec<&str> = generator.defs.iter().map(|def| def.def_name.as_str()).collect();
+        assert_eq!(kept, ["Gun_Revolver", "Gun_BoltActionRifle", "MeleeWeapon_Knife"]);
+        assert!(generator.retain_name_globs(&["Gun_[".to_string()]).is_err());
+    }
+
+    #[test]
+    fn outputs_create_directories_and_stay_inside_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("dist").join("data").join("dataset.json");
+        write_output(&nested, "{}").unwrap();
+        assert_eq!(fs::read_to_string(&nested).unwrap(), "{}");
+
+        assert_eq!(sanitize_file_name("../x"), "_x");
+        assert_eq!(sanitize_file_name("..\\..\\x"), "_.._x");
+        assert_eq!(sanitize_file_name("ThingDef"), "ThingDef");
+        assert_eq!(sanitize_file_name(".."), "_");
+    }
+
+    #[test]
+    fn no_timestamp_writes_identical_files_on_every_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let run = || {
+            let mut generator = generator(parse(DEFS), dir.path());
+            generator.timestamp = false;
+            generator.plain_json = true;
+            generator.generate_dataset_file().unwrap();
+            generator.write_summary_markdown(&dir.path().join("summary.md")).unwrap();
+            ["dataset.json.zstd", "dataset.json", "summary.md"].map(|name| fs::read(dir.path().join(name)).unwrap())
+        };
+
+        let first = run();
+        assert_eq!(first, run());
+        let dataset: serde_json::Value = serde_json::from_slice(&first[1]).unwrap();
+        assert!(dataset["stats"].get("generated_at").is_none());
+        assert!(!String::from_utf8_lossy(&first[2]).contains("Generated"));
+
+        let stats = generator(parse(DEFS), dir.path()).build_stats_json();
+        assert!(stats["generated_at"].is_string());
+    }
+
+    #[test]
+    fn datasets_of_another_format_version_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dataset.json");
+        let mut dataset = generator(parse(DEFS), dir.path()).build_dataset_value();
+        fs::write(&path, dataset.to_string()).unwrap();
+        assert_eq!(Dataset::read(&path).unwrap().format_version, FORMAT_VERSION);
+
+        dataset["format_version"] = json!(FORMAT_VERSION + 1);
+        fs::write(&path, dataset.to_string()).unwrap();
+        let error = Dataset::read(&path).unwrap_err().to_string();
+        assert!(error.contains(&format!("dataset format version {} is not supported, expected {}", FORMAT_VERSION + 1, FORMAT_VERSION)), "{}", error);
+
+        dataset.as_object_mut().unwrap().remove("format_version");
+        fs::write(&path, dataset.to_string()).unwrap();
+        assert!(Dataset::read(&path).is_err());
+    }
+
+    #[test]
+    fn flat_datasets_are_checked_against_their_stats_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut flat = generator(parse(DEFS), dir.path());
+        flat.flat = true;
+        flat.generate_dataset_file().unwrap();
+        let path = dir.path().join("dataset.json.zstd");
+        assert_eq!(Dataset::read(&path).unwrap().into_defs().count(), 1);
+
+        let sidecar = dir.path().join("dataset.stats.json");
+        fs::write(&sidecar, json!({ "format_version": 1, "stats": {} }).to_string()).unwrap();
+        assert!(Dataset::read(&path).is_err());
+    }
+
+    #[test]
+    fn flat_output_is_an_array_of_every_def() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef><defName>Gold</defName></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName></RecipeDef>
+        </Defs>"#);
+        let mut flat = generator(defs, dir.path());
+        flat.flat = true;
+
+        let data = flat.build_dataset_value();
+        let defs = data.as_array().unwrap();
+        assert_eq!(defs.len(), 3);
+        let order: Vec<(&str, &str)> = defs.iter()
+            .map(|def| (def["def_type"].as_str().unwrap(), def["def_name"].as_str().unwrap()))
+            .collect();
+        assert_eq!(order, [("RecipeDef", "Make_Steel"), ("ThingDef", "Gold"), ("ThingDef", "Steel")]);
+
+        flat.flat = false;
+        assert!(flat.build_dataset_value()["categories"].is_array());
+    }
+
+    #[test]
+    fn size_report_lists_categories_largest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName><description>A long description that makes this category the largest one.</description></ThingDef>
+            <ThingDef><defName>Gold</defName></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName></RecipeDef>
+        </Defs>"#);
+        let category_data = generator(defs, dir.path()).build_category_data();
+
+        let sizes = DatasetGenerator::category_sizes(&category_data).unwrap();
+        let names: Vec<(&str, u64)> = sizes.iter().map(|&(name, _, count)| (name, count)).collect();
+        assert_eq!(names, [("ThingDef", 2), ("RecipeDef", 1)]);
+        assert!(sizes[0].1 > sizes[1].1);
+        let total: usize = category_data.iter().map(|category| category.to_string().len()).sum();
+        assert_eq!(sizes.iter().map(|(_, size, _)| size).sum::<usize>(), total);
+    }
+
+    #[test]
+    fn file_modified_is_the_source_files_mtime() {
+        let (dir, defs) = scan(&[("Data/Core/Defs/Items.xml", DEFS)]);
+        let mtime = fs::metadata(dir.path().join("Data/Core/Defs/Items.xml")).unwrap().modified().unwrap();
+
+        let written = generator(defs.clone(), dir.path()).def_json(&defs[0]);
+        let modified = chrono::DateTime::parse_from_rfc3339(written["file_modified"].as_str().unwrap()).unwrap();
+        assert_eq!(modified.with_timezone(&Utc), chrono::DateTime::<Utc>::from(mtime));
+
+        // Parsed from a string, there is no file to take it from
+        assert!(parse(DEFS)[0].file_modified.is_none());
+    }
+
+    #[test]
+    fn trained_dictionary_round_trips_split_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let xml: String = (0..300)
+            .map(|i| format!("<ThingDef><defName>Thing_{0}</defName><label>thing {0}</label><statBases><MarketValue>{0}</MarketValue></statBases></ThingDef>", i))
+            .collect();
+        let mut split = generator(parse(&format!("<Defs>{}</Defs>", xml)), dir.path());
+        split.split_dir = Some(dir.path().join("split"));
+        split.train_dictionary().unwrap();
+        split.generate_dataset_file().unwrap();
+
+        let split_dir = dir.path().join("split");
+        let index: serde_json::Value = serde_json::from_slice(&fs::read(split_dir.join("index.json")).unwrap()).unwrap();
+        let dictionary = fs::read(split_dir.join(index["dictionary"].as_str().unwrap())).unwrap();
+        assert_eq!(&dictionary, split.dictionary.as_ref().unwrap());
+
+        let compressed = fs::read(split_dir.join("ThingDef.json.zstd")).unwrap();
+        assert!(zstd::decode_all(compressed.as_slice()).is_err());
+        let mut decoder = zstd::Decoder::with_dictionary(compressed.as_slice(), &dictionary).unwrap();
+        let mut json_data = Vec::new();
+        decoder.read_to_end(&mut json_data).unwrap();
+        let category: serde_json::Value = serde_json::from_slice(&json_data).unwrap();
+        assert_eq!(category["category"]["count"], 300);
+    }
+
+    #[test]
+    fn delta_base_keeps_added_and_modified_defs() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_path = dir.path().join("base.json");
+        let base = generator(parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName><label>steel</label></ThingDef>
+            <ThingDef><defName>Gold</defName><label>gold</label></ThingDef>
+        </Defs>"#), dir.path()).build_dataset_value();
+        fs::write(&base_path, base.to_string()).unwrap();
+
+        let mut delta = generator(parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName><label>steel</label></ThingDef>
+            <ThingDef><defName>Gold</defName><label>shiny gold</label></ThingDef>
+            <ThingDef><defName>Plasteel</defName><label>plasteel</label></ThingDef>
+        </Defs>"#), dir.path());
+        delta.apply_delta_base(&base_path).unwrap();
+
+        let data = delta.build_dataset_value();
+        let defs: Vec<(&str, &str)> = data["categories"][0]["definitions"].as_array().unwrap().iter()
+            .map(|def| (def["def_name"].as_str().unwrap(), def["delta"].as_str().unwrap()))
+            .collect();
+        assert_eq!(defs, [("Gold", "modified"), ("Plasteel", "added")]);
+    }
+
+    #[test]
+    fn source_context_captures_neighbouring_lines() {
+        let xml = "<Defs>\n  <!-- Metals -->\n  <ThingDef>\n    <defName>Steel</defName>\n  </ThingDef>\n  <ThingDef>\n    <defName>Gold</defName>\n  </ThingDef>\n</Defs>";
+        let mut parser = DefParser::new(String::new());
+        parser.source_context_lines = 2;
+        let defs = parser.parse_xml_str(xml, Path::new("Data/Core/Defs/Test.xml")).unwrap();
+
+        let context = defs[0].source_context.as_ref().unwrap();
+        assert_eq!(context.before, ["<Defs>", "  <!-- Metals -->"]);
+        assert_eq!(context.after, ["  <ThingDef>", "    <defName>Gold</defName>"]);
+        let context = defs[1].source_context.as_ref().unwrap();
+        assert_eq!(context.before, ["    <defName>Steel</defName>", "  </ThingDef>"]);
+        assert_eq!(context.after, ["</Defs>"]);
+
+        assert!(parse(xml)[0].source_context.is_none());
+    }
+
+    #[test]
+    fn overview_lists_samples_and_the_most_referenced_def() {
+        let (dir, defs) = scan(&[("Data/Core/Defs/Items.xml", r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True"><stackLimit>75</stackLimit></ThingDef>
+            <ThingDef ParentName="ResourceBase"><defName>Steel</defName><label>steel</label></ThingDef>
+            <ThingDef ParentName="ResourceBase"><defName>Gold</defName><label>gold</label></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef>
+            <RecipeDef><defName>Make_Both</defName><ingredients><Steel>1</Steel><Gold>1</Gold></ingredients></RecipeDef>
+            <RecipeDef><defName>Smelt_Slag</defName><products><Steel>5</Steel></products></RecipeDef>
+        </Defs>"#)]);
+        let path = dir.path().join("overview.json");
+        generator(defs, dir.path()).write_overview(&path).unwrap();
+
+        let overview: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(overview["stats"]["total_defs"], 6);
+        let categories = overview["categories"].as_array().unwrap();
+        let things = categories.iter().find(|category| category["name"] == "ThingDef").unwrap();
+        assert_eq!(things["count"], 3);
+        assert_eq!(things["samples"], json!(["Gold", "Steel"]));
+        assert_eq!(things["top_referenced"]["def_name"], "Steel");
+        assert_eq!(things["top_referenced"]["references_in"], 3);
+        let recipes = categories.iter().find(|category| category["name"] == "RecipeDef").unwrap();
+        assert!(recipes["top_referenced"].is_null());
+    }
+
+    #[test]
+    fn json_pointer_resolves_into_flattened_elements() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs = parse("<Defs><ThingDef><defName>Steel</defName><statBases><MarketValue>1.9</MarketValue></statBases></ThingDef></Defs>");
+        let def_json = generator(defs.clone(), dir.path()).def_json(&defs[0]);
+
+        let elements = def_json["elements"].as_array().unwrap();
+        let index = elements.iter().position(|element| element["name"] == "MarketValue").unwrap();
+        assert_eq!(json_pointer_value(&def_json, &format!("/elements/{}/content", index)).unwrap(), "1.9");
+        assert_eq!(json_pointer_value(&def_json, "/def_name").unwrap(), "Steel");
+        assert_eq!(json_pointer_value(&def_json, "/is_abstract").unwrap(), "false");
+        assert_eq!(json_pointer_value(&def_json, "/elements/99/content").unwrap(), "(not found)");
+    }
+
+    #[test]
+    fn gzip_and_plain_json_hold_the_dataset() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut both = generator(parse(DEFS), dir.path());
+        both.gzip = true;
+        both.plain_json = true;
+        both.timestamp = false;
+        both.generate_dataset_file().unwrap();
+
+        let expected = both.build_dataset_value();
+        let mut json_data = Vec::new();
+        flate2::read::GzDecoder::new(fs::File::open(dir.path().join("dataset.json.gz")).unwrap())
+            .read_to_end(&mut json_data)
+            .unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&json_data).unwrap(), expected);
+        assert_eq!(fs::read(dir.path().join("dataset.json")).unwrap(), json_data);
+        let zstd_data = zstd::decode_all(fs::File::open(dir.path().join("dataset.json.zstd")).unwrap()).unwrap();
+        assert_eq!(zstd_data, json_data);
+    }
+
+    #[test]
+    fn grep_matches_defs_by_their_xml() {
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName><statBases><MarketValue>1.9</MarketValue></statBases></ThingDef>
+            <ThingDef><defName>Gold</defName><statBases><MarketValue>10</MarketValue></statBases></ThingDef>
+        </Defs>"#);
+        let regex = RegexBuilder::new(r"marketvalue>1\.9<").case_insensitive(true).build().unwrap();
+
+        assert_eq!(grep_def(&defs[0], &regex), Some(vec!["<MarketValue>1.9</MarketValue>"]));
+        assert_eq!(grep_def(&defs[1], &regex), None);
+        assert_eq!(grep_def(&defs[0], &Regex::new(r"marketvalue>1\.9<").unwrap()), None);
+    }
+
+    #[test]
+    fn summary_markdown_lists_stats_and_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <ThingDef><defName>Gold</defName></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName></RecipeDef>
+        </Defs>"#);
+        let path = dir.path().join("docs").join("DEFS.md");
+        generator(defs, dir.path()).write_summary_markdown(&path).unwrap();
+
+        let markdown = fs::read_to_string(&path).unwrap();
+        assert!(markdown.starts_with("# Definitions\n\n- **Definitions:** 3\n- **Def types:** 2\n- **Files:** 1\n"));
+        assert!(markdown.contains("- **Generated:** "));
+        assert!(markdown.contains("| Def type | Count |\n|---|---:|\n| RecipeDef | 1 |\n| ThingDef | 2 |\n"));
+        assert!(markdown.contains("<summary>ThingDef (2)</summary>\n\n- `Gold`\n- `Steel`\n"));
+    }
+
+    #[test]
+    fn preserve_order_follows_the_source() {
+        let (dir, defs) = scan(&[
+            ("Data/Core/Defs/A.xml", "<Defs><ThingDef><defName>Zinc</defName></ThingDef><ThingDef><defName>Steel</defName></ThingDef></Defs>"),
+            ("Data/Core/Defs/B.xml", "<Defs><ThingDef><defName>Apple</defName></ThingDef><ThingDef><defName>Gold</defName></ThingDef></Defs>"),
+        ]);
+        let names = |data: &serde_json::Value| -> Vec<String> {
+            data["categories"][0]["definitions"].as_array().unwrap().iter()
+                .map(|def| def["def_name"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let mut ordered = generator(defs, dir.path());
+        assert_eq!(names(&ordered.build_dataset_value()), ["Apple", "Gold", "Steel", "Zinc"]);
+        ordered.preserve_order = true;
+        let data = ordered.build_dataset_value();
+        assert_eq!(names(&data), ["Zinc", "Steel", "Apple", "Gold"]);
+        let sequences: Vec<u64> = data["categories"][0]["definitions"].as_array().unwrap().iter()
+            .map(|def| def["sequence"].as_u64().unwrap())
+            .collect();
+        assert!(sequences.is_sorted());
+
+        ordered.flat = true;
+        let flat = ordered.build_dataset_value();
+        let flat: Vec<&str> = flat.as_array().unwrap().iter().map(|def| def["def_name"].as_str().unwrap()).collect();
+        assert_eq!(flat, ["Zinc", "Steel", "Apple", "Gold"]);
+    }
+
+    #[test]
+    fn stdin_documents_become_one_json_line_per_def() {
+        let mut out = Vec::new();
+        write_defs_ndjson(r#"<Defs>
+            <ThingDef><defName>Steel</defName><label>steel</label></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef>
+        </Defs>"#, &mut out).unwrap();
+
+        let defs: Vec<RimWorldDef> = String::from_utf8(out).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(defs.len(), 2);
+        assert_eq!((defs[0].def_name.as_str(), defs[0].label.as_deref()), ("Steel", Some("steel")));
+        assert_eq!(defs[1].file_path, "<stdin>");
+        assert!(defs[1].references_out.is_empty());
+
+        assert!(write_defs_ndjson("<Defs><ThingDef></Defs>", &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn same_named_defs_are_ordered_by_extension_then_file() {
+        let (dir, defs) = scan(&[
+            ("Data/Royalty/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName><label>royalty</label></ThingDef></Defs>"),
+            ("Data/Core/Defs/B.xml", "<Defs><ThingDef><defName>Steel</defName><label>core b</label></ThingDef></Defs>"),
+            ("Data/Core/Defs/A.xml", "<Defs><ThingDef><defName>Steel</defName><label>core a 2</label></ThingDef><ThingDef><defName>Steel</defName><label>core a 1</label></ThingDef></Defs>"),
+        ]);
+        let labels = |defs: Vec<RimWorldDef>| -> Vec<String> {
+            let data = generator(defs, dir.path()).build_dataset_value();
+            data["categories"][0]["definitions"].as_array().unwrap().iter()
+                .map(|def| def["label"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let expected = ["core a 1", "core a 2", "core b", "royalty"];
+        assert_eq!(labels(defs.clone()), expected);
+        assert_eq!(labels(defs.into_iter().rev().collect()), expected);
+    }
+
+    #[test]
+    fn recursion_limit_is_configurable_and_bounds_every_walk() {
+        let nested = |depth: usize| format!("<Defs><ThingDef><defName>Deep</defName>{}Steel{}</ThingDef></Defs>",
+            "<a>".repeat(depth), "</a>".repeat(depth));
+        let mut parser = DefParser::new(String::new());
+        parser.recursion_limit = 8;
+        let path = Path::new("Data/Core/Defs/Deep.xml");
+
+        let error = parser.parse_xml_str(&nested(8), path).unwrap_err();
+        assert!(error.to_string().contains("nested deeper than 8 levels"), "{}", error);
+
+        let defs = parser.parse_xml_str(&nested(6), path).unwrap();
+        assert!(defs[0].has_element("a"));
+        assert_eq!(parser.parse_xml_str(&format!("<Defs>{}</Defs>", defs[0].raw_xml), path).unwrap()[0].raw_xml, defs[0].raw_xml);
+        let dir = tempfile::tempdir().unwrap();
+        let mut nested_output = generator(defs.clone(), dir.path());
+        nested_output.nested_elements = true;
+        let mut element = &nested_output.def_json(&defs[0])["elements"][1];
+        for _ in 1..6 {
+            element = &element["children"][0];
+        }
+        assert_eq!((&element["name"], &element["content"]), (&json!("a"), &json!("Steel")));
+    }
+
+    #[test]
+    fn nested_elements_keep_parents_and_children_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs = parse(r#"<Defs><ThingDef>
+            <defName>Steel</defName>
+            <statBases><MarketValue>1.9</MarketValue><Mass>0.5</Mass></statBases>
+            <graphicData><texPath>Things/Steel</texPath><shaderType Mode="Cutout">CutoutComplex</shaderType></graphicData>
+        </ThingDef></Defs>"#);
+        let mut nested = generator(defs.clone(), dir.path());
+        nested.nested_elements = true;
+
+        let elements = nested.def_json(&defs[0])["elements"].clone();
+        assert_eq!(elements[1]["name"], "statBases");
+        let stats: Vec<(&str, &str)> = elements[1]["children"].as_array().unwrap().iter()
+            .map(|stat| (stat["name"].as_str().unwrap(), stat["content"].as_str().unwrap()))
+            .collect();
+        assert_eq!(stats, [("MarketValue", "1.9"), ("Mass", "0.5")]);
+        assert_eq!(elements[2]["children"][1]["attributes"], json!({ "Mode": "Cutout" }));
+
+        nested.nested_elements = false;
+        let flat = nested.def_json(&defs[0])["elements"].clone();
+        assert_eq!(flat[2]["name"], "MarketValue");
+        assert!(flat[2].get("children").is_none());
+    }
+
+    #[test]
+    fn only_referenced_drops_unreferenced_defs_outside_the_keep_types() {
+        let (dir, defs) = scan(&[("Data/Core/Defs/Defs.xml", r#"<Defs>
+            <ThingDef><defName>Steel</defName></ThingDef>
+            <StuffCategoryDef><defName>Metallic</defName></StuffCategoryDef>
+            <StuffCategoryDef><defName>Unused</defName></StuffCategoryDef>
+            <ResearchProjectDef><defName>Smithing</defName></ResearchProjectDef>
+            <ThingDef><defName>Plasteel</defName><stuffCategories><li>Metallic</li></stuffCategories></ThingDef>
+        </Defs>"#)]);
+        let mut filtered = generator(defs, dir.path());
+        filtered.retain_referenced(&DEFAULT_KEEP_TYPES.iter().map(|t| t.to_string()).collect::<Vec<_>>());
+
+        let mut names: Vec<&str> = filtered.defs.iter().map(|def| def.def_name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["Metallic", "Plasteel", "Smithing", "Steel"]);
+
+        filtered.retain_referenced(&[]);
+        assert_eq!(filtered.defs.iter().map(|def| def.def_name.as_str()).collect::<Vec<_>>(), ["Metallic"]);
+    }
+
+    #[test]
+    fn summary_combines_label_and_first_sentence() {
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Steel</defName><label>steel</label><description>Metal from the ground. It is used for 1.5 things.</description></ThingDef>
+            <ThingDef><defName>Gold</defName><description>  </description></ThingDef>
+            <ThingDef><defName>Jade</defName><label>jade</label><description>A precious green stone with v1.2 polish</description></ThingDef>
+        </Defs>"#);
+
+        assert_eq!(def_summary(&defs[0], 120), "steel — Metal from the ground.");
+        assert_eq!(def_summary(&defs[1], 120), "Gold");
+        assert_eq!(def_summary(&defs[2], 120), "jade — A precious green stone with v1.2 polish");
+
+        let truncated = def_summary(&defs[0], 16);
+        assert_eq!(truncated, "steel — Metal f…");
+        assert_eq!(truncated.chars().count(), 16);
+        assert_eq!(def_summary(&defs[0], 14), "steel — Metal…");
+    }
+
+    #[test]
+    fn has_element_filter_matches_all_or_any_tags() {
+        let defs = parse(r#"<Defs>
+            <ThingDef><defName>Both</defName><comps><li><compClass>CompGlower</compClass></li></comps><stuffProps/></ThingDef>
+            <ThingDef><defName>CompsOnly</defName><comps/></ThingDef>
+            <ThingDef><defName>Neither</defName><label>neither</label></ThingDef>
+        </Defs>"#);
+        let tags = ["comps".to_string(), "stuffProps".to_string()];
+        let tags: Vec<&String> = tags.iter().collect();
+        let dir = tempfile::tempdir().unwrap();
+        let kept = |match_any: bool| {
+            let mut generator = generator(defs.clone(), dir.path());
+            generator.retain_elements(&tags, match_any);
+            generator.defs.iter().map(|def| def.def_name.clone()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(kept(false), ["Both"]);
+        assert_eq!(kept(true), ["Both", "CompsOnly"]);
+    }
+
+    #[test]
+    fn merge_same_name_keeps_every_source_as_a_variant() {
+        let (dir, defs) = scan(&[
+            ("Data/Core/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName><label>core</label></ThingDef><ThingDef><defName>Gold</defName></ThingDef></Defs>"),
+            ("Data/Royalty/Defs/Items.xml", "<Defs><ThingDef><defName>Steel</defName><label>royalty</label></ThingDef></Defs>"),
+            ("Data/Core/Defs/Recipes.xml", "<Defs><RecipeDef><defName>Smelt</defName><products><Steel>10</Steel></products></RecipeDef></Defs>"),
+        ]);
+        let mut generator = generator(defs, dir.path());
+        generator.merge_same_name = true;
+        let data = generator.build_dataset_value();
+        let things = data["categories"].as_array().unwrap().iter()
+            .find(|category| category["name"] == "ThingDef").unwrap()["definitions"].as_array().unwrap();
+
+        assert_eq!(things.len(), 2);
+        let steel = things.iter().find(|def| def["def_name"] == "Steel").unwrap();
+        assert_eq!(steel["label"], "core");
+        assert_eq!(steel["references_in"], json!(["Smelt"]));
+        let variants = steel["variants"].as_array().unwrap();
+        assert_eq!(variants.iter().map(|v| (v["extension"].as_str().unwrap(), v["file_path"].as_str().unwrap())).collect::<Vec<_>>(),
+            [("Core", "Data/Core/Defs/Items.xml"), ("Royalty", "Data/Royalty/Defs/Items.xml")]);
+        assert!(variants[1]["raw_xml"].as_str().unwrap().contains("<label>royalty</label>"));
+        let gold = things.iter().find(|def| def["def_name"] == "Gold").unwrap();
+        assert_eq!(gold["variants"].as_array().unwrap().len(), 1);
+    }
+}
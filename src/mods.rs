@@ -0,0 +1,185 @@
+//! Mod metadata: where the defs of mod folders scanned with `--mod-dir` live, and the
+//! package id and name from `About/About.xml` that every def is attributed to.
+
+use anyhow::Result;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Package ids and names of the official content, by the extension its defs are detected as.
+const OFFICIAL_MODS: &[(&str, &str, &str)] = &[
+    ("Core", "Ludeon.RimWorld", "Core"),
+    ("Royalty", "Ludeon.RimWorld.Royalty", "Royalty"),
+    ("Ideology", "Ludeon.RimWorld.Ideology", "Ideology"),
+    ("Biotech", "Ludeon.RimWorld.Biotech", "Biotech"),
+    ("Anomaly", "Ludeon.RimWorld.Anomaly", "Anomaly"),
+    ("Odyssey", "Ludeon.RimWorld.Odyssey", "Odyssey"),
+];
+
+/// Identity of a mod as declared in its `About/About.xml`.
+#[derive(Debug, Clone)]
+pub struct ModInfo {
+    pub package_id: String,
+    pub name: Option<String>,
+}
+
+impl ModInfo {
+    /// Reads `About/About.xml` below `mod_dir`, or None when the folder has no About.xml.
+    pub fn read(mod_dir: &Path) -> Result<Option<Self>> {
+        let about_path = mod_dir.join("About").join("About.xml");
+        if !about_path.is_file() {
+            return Ok(None);
+        }
+
+        let mut reader = Reader::from_file(&about_path)?;
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut stack: Vec<String> = Vec::new();
+        let mut package_id = None;
+        let mut name = None;
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) => stack.push(String::from_utf8_lossy(e.local_name().as_ref()).to_string()),
+                Event::End(_) => {
+                    stack.pop();
+                }
+                Event::Text(e) => {
+                    let text = e.unescape()?.trim().to_string();
+                    match stack.iter().map(|name| name.as_str()).collect::<Vec<_>>().as_slice() {
+                        ["ModMetaData", "packageId"] => package_id = Some(text),
+                        ["ModMetaData", "name"] => name = Some(text),
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        Ok(package_id.map(|package_id| ModInfo { package_id, name }))
+    }
+
+    /// The official content detected as `extension`, for installs without About.xml files.
+    pub fn official(extension: &str) -> Option<Self> {
+        OFFICIAL_MODS.iter()
+            .find(|(official, _, _)| *official == extension)
+            .map(|(_, package_id, name)| ModInfo {
+                package_id: package_id.to_string(),
+                name: Some(name.to_string()),
+            })
+    }
+}
+
+/// Whether `package_id` is official content, whose defs are scanned from Data.
+pub fn is_official(package_id: &str) -> bool {
+    OFFICIAL_MODS.iter().any(|(_, official, _)| official.eq_ignore_ascii_case(package_id))
+}
+
+/// The mod folders directly below each of `roots` (`<RimWorld>/Mods`, the Steam workshop
+/// folder, ...), by lowercase package id. The first root holding a package id wins.
+pub fn discover(roots: &[PathBuf]) -> HashMap<String, PathBuf> {
+    let mut found = HashMap::new();
+    for root in roots {
+        let mut mod_dirs: Vec<PathBuf> = fs::read_dir(root).into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        mod_dirs.sort();
+        for mod_dir in mod_dirs {
+            if let Some(info) = ModInfo::read(&mod_dir).ok().flatten() {
+                found.entry(info.package_id.to_lowercase()).or_insert(mod_dir);
+            }
+        }
+    }
+    found
+}
+
+/// The Defs folders of a mod: `Defs`, `Common/Defs` and `<version>/Defs` of the newest
+/// version folder (`1.5`, `1.4`, ...). Older version folders hold the same defs for
+/// older game versions, so scanning them too would duplicate every def.
+pub fn def_dirs(mod_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = [mod_dir.join("Defs"), mod_dir.join("Common").join("Defs")]
+        .into_iter()
+        .filter(|dir| dir.is_dir())
+        .collect();
+
+    let newest_version = fs::read_dir(mod_dir).into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let version = parse_version(&entry.file_name().to_string_lossy())?;
+            Some((version, entry.path().join("Defs")))
+        })
+        .filter(|(_, defs)| defs.is_dir())
+        .max_by(|a, b| a.0.cmp(&b.0));
+    dirs.extend(newest_version.map(|(_, defs)| defs));
+
+    dirs
+}
+
+/// The folders of a mod that hold content the way `Data/<content>` does: the mod folder,
+/// `Common` and the newest version folder, each with its own `Textures`, `Sounds`, ...
+pub fn content_roots(mod_dir: &Path) -> Vec<PathBuf> {
+    let newest_version = fs::read_dir(mod_dir).into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| Some((parse_version(&entry.file_name().to_string_lossy())?, entry.path())))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, path)| path);
+
+    [mod_dir.to_path_buf(), mod_dir.join("Common")].into_iter()
+        .chain(newest_version)
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// `1.5` as (1, 5); None for folders that aren't version numbers.
+fn parse_version(name: &str) -> Option<(u32, u32)> {
+    let (major, minor) = name.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn about_xml_and_official_content_give_the_package_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let mod_dir = dir.path().join("Guns");
+        fs::create_dir_all(mod_dir.join("About")).unwrap();
+        assert!(ModInfo::read(&mod_dir).unwrap().is_none());
+
+        fs::write(mod_dir.join("About").join("About.xml"),
+            "<ModMetaData><name>Laser Guns</name><packageId>Author.Guns</packageId><modDependencies><li><packageId>Other.Mod</packageId></li></modDependencies></ModMetaData>").unwrap();
+        let info = ModInfo::read(&mod_dir).unwrap().unwrap();
+        assert_eq!((info.package_id.as_str(), info.name.as_deref()), ("Author.Guns", Some("Laser Guns")));
+
+        assert_eq!(ModInfo::official("Core").unwrap().package_id, "Ludeon.RimWorld");
+        assert_eq!(ModInfo::official("Royalty").unwrap().package_id, "Ludeon.RimWorld.Royalty");
+        assert!(ModInfo::official("Unknown").is_none());
+        assert!(is_official("ludeon.rimworld.biotech"));
+        assert!(!is_official("Author.Guns"));
+    }
+
+    #[test]
+    fn def_dirs_skip_older_version_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        for folder in ["Defs", "Common/Defs", "1.4/Defs", "1.5/Defs", "1.6", "Textures"] {
+            fs::create_dir_all(dir.path().join(folder)).unwrap();
+        }
+
+        assert_eq!(def_dirs(dir.path()), [
+            dir.path().join("Defs"),
+            dir.path().join("Common").join("Defs"),
+            dir.path().join("1.5").join("Defs"),
+        ]);
+    }
+}
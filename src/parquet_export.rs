@@ -0,0 +1,179 @@
+//! Columnar export for data analysis (`--format parquet`, requires the `parquet` feature).
+//!
+//! `defs.parquet`, one row per def:
+//!
+//! | column            | type          | notes                                   |
+//! |-------------------|---------------|-----------------------------------------|
+//! | `def_name`        | Utf8          |                                         |
+//! | `def_type`        | Utf8          | e.g. `ThingDef`                         |
+//! | `label`           | Utf8, null    |                                         |
+//! | `description`     | Utf8, null    |                                         |
+//! | `parent_name`     | Utf8, null    | `ParentName` attribute                  |
+//! | `is_abstract`     | Boolean       |                                         |
+//! | `file_path`       | Utf8          | relative to the RimWorld directory      |
+//! | `extension`       | Utf8          | Core, Royalty, ..., Unknown             |
+//! | `element_count`   | UInt64        | 0 for defs without elements             |
+//! | `max_depth`       | UInt64        |                                         |
+//! | `references_out`  | UInt64        | number of outgoing references           |
+//! | `references_in`   | UInt64        | number of incoming references           |
+//!
+//! `references.parquet`, one row per outgoing reference and field it was found in:
+//!
+//! | column        | type | notes                                          |
+//! |---------------|------|------------------------------------------------|
+//! | `source_name` | Utf8 | defName of the referencing def                 |
+//! | `source_type` | Utf8 |                                                |
+//! | `target_name` | Utf8 | defName of the referenced def                  |
+//! | `field_path`  | Utf8 | element it was found in, e.g. `costList.Steel` |
+//!
+//! Both read directly with e.g. `pandas.read_parquet`, `polars.read_parquet` or
+//! `SELECT * FROM 'defs.parquet'` in DuckDB.
+
+use anyhow::Result;
+use arrow::array::{ArrayRef, BooleanArray, StringArray, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{create_output_file, Reference, RimWorldDef};
+
+pub fn write_parquet(defs: &[RimWorldDef], dir: &Path) -> Result<()> {
+    println!("\nWriting Parquet files...");
+
+    let defs_path = dir.join("defs.parquet");
+    write_batch(&defs_path, defs_batch(defs)?)?;
+    println!("  ✓ {} definitions written: {}", defs.len(), defs_path.display());
+
+    let references = references_batch(defs)?;
+    let references_path = dir.join("references.parquet");
+    let reference_count = references.num_rows();
+    write_batch(&references_path, references)?;
+    println!("  ✓ {} references written: {}", reference_count, references_path.display());
+
+    Ok(())
+}
+
+fn defs_batch(defs: &[RimWorldDef]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("def_name", DataType::Utf8, false),
+        Field::new("def_type", DataType::Utf8, false),
+        Field::new("label", DataType::Utf8, true),
+        Field::new("description", DataType::Utf8, true),
+        Field::new("parent_name", DataType::Utf8, true),
+        Field::new("is_abstract", DataType::Boolean, false),
+        Field::new("file_path", DataType::Utf8, false),
+        Field::new("extension", DataType::Utf8, false),
+        Field::new("element_count", DataType::UInt64, false),
+        Field::new("max_depth", DataType::UInt64, false),
+        Field::new("references_out", DataType::UInt64, false),
+        Field::new("references_in", DataType::UInt64, false),
+    ]);
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(defs.iter().map(|def| &def.def_name))),
+        Arc::new(StringArray::from_iter_values(defs.iter().map(|def| &def.def_type))),
+        Arc::new(StringArray::from(defs.iter().map(|def| def.label.as_deref()).collect::<Vec<_>>())),
+        Arc::new(StringArray::from(defs.iter().map(|def| def.description.as_deref()).collect::<Vec<_>>())),
+        Arc::new(StringArray::from(defs.iter().map(|def| def.parent_name.as_deref()).collect::<Vec<_>>())),
+        Arc::new(BooleanArray::from(defs.iter().map(|def| def.is_abstract).collect::<Vec<_>>())),
+        Arc::new(StringArray::from_iter_values(defs.iter().map(|def| &def.file_path))),
+        Arc::new(StringArray::from_iter_values(defs.iter().map(|def| &def.extension))),
+        Arc::new(UInt64Array::from_iter_values(defs.iter()
+            .map(|def| def.stats.as_ref().map_or(0, |stats| stats.element_count as u64)))),
+        Arc::new(UInt64Array::from_iter_values(defs.iter()
+            .map(|def| def.stats.as_ref().map_or(0, |stats| stats.max_depth as u64)))),
+        Arc::new(UInt64Array::from_iter_values(defs.iter().map(|def| def.references_out.len() as u64))),
+        Arc::new(UInt64Array::from_iter_values(defs.iter().map(|def| def.references_in.len() as u64))),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+fn references_batch(defs: &[RimWorldDef]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("source_name", DataType::Utf8, false),
+        Field::new("source_type", DataType::Utf8, false),
+        Field::new("target_name", DataType::Utf8, false),
+        Field::new("field_path", DataType::Utf8, false),
+    ]);
+
+    let edges: Vec<(&RimWorldDef, &Reference)> = defs.iter()
+        .flat_map(|def| def.references.iter().map(move |reference| (def, reference)))
+        .collect();
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(edges.iter().map(|(def, _)| &def.def_name))),
+        Arc::new(StringArray::from_iter_values(edges.iter().map(|(def, _)| &def.def_type))),
+        Arc::new(StringArray::from_iter_values(edges.iter().map(|(_, reference)| &reference.def_name))),
+        Arc::new(StringArray::from_iter_values(edges.iter().map(|(_, reference)| &reference.field_path))),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+fn write_batch(path: &Path, batch: RecordBatch) -> Result<()> {
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(create_output_file(path)?, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::AsArray;
+    use arrow::datatypes::UInt64Type;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs;
+
+    use crate::DefParser;
+
+    fn read_back(path: &Path) -> Vec<RecordBatch> {
+        ParquetRecordBatchReaderBuilder::try_new(fs::File::open(path).unwrap()).unwrap()
+            .build().unwrap()
+            .collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn written_files_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs_dir = dir.path().join("Data").join("Core").join("Defs");
+        fs::create_dir_all(&defs_dir).unwrap();
+        fs::write(defs_dir.join("Items.xml"), r#"<Defs>
+            <ThingDef><defName>Steel</defName><label>steel</label></ThingDef>
+            <RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef>
+        </Defs>"#).unwrap();
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.scan_defs_directory().unwrap();
+
+        let out = dir.path().join("out");
+        write_parquet(&parser.parsed_defs, &out).unwrap();
+
+        let defs = read_back(&out.join("defs.parquet"));
+        assert_eq!(defs.iter().map(RecordBatch::num_rows).sum::<usize>(), 2);
+        let batch = &defs[0];
+        let column = |name: &str| batch.column_by_name(name).unwrap();
+        let names: Vec<&str> = column("def_name").as_string::<i32>().iter().flatten().collect();
+        assert_eq!(names, ["Steel", "Make_Steel"]);
+        let labels = column("label");
+        assert_eq!(labels.as_string::<i32>().value(0), "steel");
+        assert!(labels.is_null(1));
+        let references_in: Vec<u64> = column("references_in").as_primitive::<UInt64Type>().values().to_vec();
+        assert_eq!(references_in, [1, 0]);
+
+        let references = read_back(&out.join("references.parquet"));
+        let batch = &references[0];
+        assert_eq!(batch.num_rows(), 1);
+        let value = |name: &str| batch.column_by_name(name).unwrap().as_string::<i32>().value(0).to_string();
+        assert_eq!((value("source_name"), value("target_name"), value("field_path")),
+            ("Make_Steel".to_string(), "Steel".to_string(), "products.Steel".to_string()));
+    }
+}
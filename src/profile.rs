@@ -0,0 +1,25 @@
+//! Sampling profiler for `--profile`, writing a flamegraph SVG of the whole run.
+//! Only compiled with the `profile` feature since pprof needs a Unix target.
+
+use anyhow::Result;
+use pprof::{ProfilerGuard, ProfilerGuardBuilder};
+use std::path::Path;
+
+use crate::create_output_file;
+
+const SAMPLE_FREQUENCY: i32 = 997;  // Hz, prime so samples don't align with periodic work
+
+pub fn start() -> Result<ProfilerGuard<'static>> {
+    let guard = ProfilerGuardBuilder::default()
+        .frequency(SAMPLE_FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()?;
+    Ok(guard)
+}
+
+pub fn write_flamegraph(guard: &ProfilerGuard<'_>, path: &Path) -> Result<()> {
+    let report = guard.report().build()?;
+    report.flamegraph(create_output_file(path)?)?;
+    println!("  ✓ Profile written to {}", path.display());
+    Ok(())
+}
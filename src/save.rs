@@ -0,0 +1,97 @@
+//! Reading active mod lists, from the `<meta>` header of a RimWorld save (`.rws`) or from
+//! the game's `ModsConfig.xml`. Only a save's header is parsed, so large saves are not
+//! read past their first few lines.
+
+use anyhow::Result;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::Path;
+
+/// A mod as listed in the save, in load order.
+#[derive(Debug, Clone)]
+pub struct SaveMod {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SaveModList {
+    pub game_version: Option<String>,
+    pub mods: Vec<SaveMod>,
+}
+
+impl SaveModList {
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut reader = Reader::from_file(path)?;
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut stack: Vec<String> = Vec::new();
+        let mut game_version = None;
+        let mut ids = Vec::new();
+        let mut names = Vec::new();
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) => {
+                    stack.push(String::from_utf8_lossy(e.local_name().as_ref()).to_string());
+                }
+                Event::End(e) => {
+                    stack.pop();
+                    // Everything we need lives in <meta>, the rest of the save is skipped
+                    if e.local_name().as_ref() == b"meta" {
+                        break;
+                    }
+                }
+                Event::Text(e) => {
+                    let text = e.unescape()?.trim().to_string();
+                    let path: Vec<&str> = stack.iter().map(|name| name.as_str()).collect();
+                    match path.as_slice() {
+                        [.., "meta", "gameVersion"] => game_version = Some(text),
+                        [.., "meta", "modIds", "li"] => ids.push(text),
+                        [.., "meta", "modNames", "li"] => names.push(text),
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        // modNames is parallel to modIds but missing from very old saves
+        let mut names = names.into_iter();
+        let mods = ids.into_iter()
+            .map(|id| SaveMod { id, name: names.next() })
+            .collect();
+
+        Ok(Self { game_version, mods })
+    }
+}
+
+/// Package ids in `<activeMods>` of a `ModsConfig.xml`, in load order.
+pub fn read_active_mods(path: &Path) -> Result<Vec<String>> {
+    let mut reader = Reader::from_file(path)?;
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut active_mods = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => stack.push(String::from_utf8_lossy(e.local_name().as_ref()).to_string()),
+            Event::End(_) => {
+                stack.pop();
+            }
+            Event::Text(e) if stack.iter().rev().take(2).map(|name| name.as_str()).eq(["li", "activeMods"]) => {
+                active_mods.push(e.unescape()?.trim().to_string());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(active_mods)
+}
@@ -0,0 +1,63 @@
+//! Full-text search index export (`--emit-tantivy`, requires the `tantivy` feature).
+//!
+//! Schema, one document per def:
+//!
+//! | field         | options          | notes                                      |
+//! |---------------|------------------|--------------------------------------------|
+//! | `def_name`    | TEXT, STORED     | tokenized, so `Gun_Revolver` matches `revolver` |
+//! | `label`       | TEXT, STORED     |                                            |
+//! | `description` | TEXT             | searchable only, not stored                |
+//! | `def_type`    | STRING, STORED   | exact match, e.g. `def_type:ThingDef`      |
+//! | `extension`   | STRING, STORED   | exact match, e.g. `extension:Biotech`      |
+//!
+//! Open the directory with `tantivy::Index::open_in_dir` and parse queries with a
+//! `QueryParser` over `def_name`, `label` and `description`, e.g.
+//! `label:steel AND def_type:ThingDef`.
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use tantivy::schema::{Schema, STORED, STRING, TEXT};
+use tantivy::{Index, IndexWriter, TantivyDocument};
+
+use crate::RimWorldDef;
+
+pub fn write_index(defs: &[RimWorldDef], index_dir: &Path) -> Result<()> {
+    println!("\nBuilding Tantivy index in {}...", index_dir.display());
+
+    let mut schema_builder = Schema::builder();
+    let def_name = schema_builder.add_text_field("def_name", TEXT | STORED);
+    let label = schema_builder.add_text_field("label", TEXT | STORED);
+    let description = schema_builder.add_text_field("description", TEXT);
+    let def_type = schema_builder.add_text_field("def_type", STRING | STORED);
+    let extension = schema_builder.add_text_field("extension", STRING | STORED);
+    let schema = schema_builder.build();
+
+    // create_in_dir refuses to overwrite an existing index, so the previous run's is removed.
+    // A folder without meta.json isn't an index and is left alone.
+    if index_dir.join("meta.json").is_file() {
+        fs::remove_dir_all(index_dir)?;
+    }
+    fs::create_dir_all(index_dir)?;
+    let index = Index::create_in_dir(index_dir, schema)?;
+    let mut writer: IndexWriter = index.writer(50_000_000)?;
+
+    for def in defs {
+        let mut doc = TantivyDocument::default();
+        doc.add_text(def_name, &def.def_name);
+        if let Some(text) = &def.label {
+            doc.add_text(label, text);
+        }
+        if let Some(text) = &def.description {
+            doc.add_text(description, text);
+        }
+        doc.add_text(def_type, &def.def_type);
+        doc.add_text(extension, &def.extension);
+        writer.add_document(doc)?;
+    }
+
+    writer.commit()?;
+    println!("  ✓ {} documents indexed", defs.len());
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! Relational export for SQL queries (`--format sqlite`, requires the `sqlite` feature).
+//! Everything goes into one `defs.sqlite` file, which is replaced on every run:
+//!
+//! ```sql
+//! CREATE TABLE defs (
+//!     def_name    TEXT NOT NULL,
+//!     def_type    TEXT NOT NULL,     -- e.g. ThingDef
+//!     label       TEXT,
+//!     description TEXT,
+//!     extension   TEXT NOT NULL,     -- Core, Royalty, ..., Unknown
+//!     file_path   TEXT NOT NULL,     -- relative to the RimWorld directory
+//!     is_abstract INTEGER NOT NULL   -- 0 or 1
+//! );
+//! CREATE TABLE tags (def_name TEXT NOT NULL, tag TEXT NOT NULL);
+//! CREATE TABLE "references" (
+//!     src        TEXT NOT NULL,      -- defName of the referencing def
+//!     dst        TEXT NOT NULL,      -- defName, or C# class for kind 'code'
+//!     kind       TEXT NOT NULL,      -- 'def', 'parent' (ParentName) or 'code' (Class attribute)
+//!     field_path TEXT                -- element the def reference was found in, e.g. costList.Steel
+//! );
+//! CREATE TABLE elements (
+//!     def_name TEXT NOT NULL,
+//!     path     TEXT NOT NULL,        -- dotted path from the def root, e.g. statBases.MarketValue
+//!     name     TEXT NOT NULL,
+//!     content  TEXT
+//! );
+//! ```
+//!
+//! `references` is an SQL keyword, so that table name needs quotes in queries:
+//! `SELECT dst, COUNT(*) FROM "references" WHERE kind = 'def' GROUP BY dst`.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::Path;
+
+use crate::{DefElement, RimWorldDef};
+
+const SCHEMA: &str = "
+    CREATE TABLE defs (
+        def_name TEXT NOT NULL,
+        def_type TEXT NOT NULL,
+        label TEXT,
+        description TEXT,
+        extension TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        is_abstract INTEGER NOT NULL
+    );
+    CREATE TABLE tags (def_name TEXT NOT NULL, tag TEXT NOT NULL);
+    CREATE TABLE \"references\" (src TEXT NOT NULL, dst TEXT NOT NULL, kind TEXT NOT NULL, field_path TEXT);
+    CREATE TABLE elements (def_name TEXT NOT NULL, path TEXT NOT NULL, name TEXT NOT NULL, content TEXT);
+    CREATE INDEX defs_name ON defs (def_name);
+    CREATE INDEX tags_def_name ON tags (def_name);
+    CREATE INDEX references_src ON \"references\" (src);
+    CREATE INDEX references_dst ON \"references\" (dst);
+    CREATE INDEX elements_def_name ON elements (def_name);
+";
+
+pub fn write_sqlite(defs: &[RimWorldDef], path: &Path) -> Result<()> {
+    println!("\nWriting SQLite database...");
+
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch(SCHEMA)?;
+
+    // One transaction per table, row by row inserts are slow in autocommit mode
+    let tx = connection.transaction()?;
+    {
+        let mut insert = tx.prepare("INSERT INTO defs VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+        for def in defs {
+            insert.execute(params![def.def_name, def.def_type, def.label, def.description,
+                def.extension, def.file_path, def.is_abstract])?;
+        }
+    }
+    tx.commit()?;
+
+    let tx = connection.transaction()?;
+    {
+        let mut insert = tx.prepare("INSERT INTO tags VALUES (?1, ?2)")?;
+        for def in defs {
+            for tag in &def.tags {
+                insert.execute(params![def.def_name, tag])?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    let tx = connection.transaction()?;
+    let mut reference_count = 0;
+    {
+        let mut insert = tx.prepare("INSERT INTO \"references\" VALUES (?1, ?2, ?3, ?4)")?;
+        for def in defs {
+            for reference in &def.references {
+                insert.execute(params![def.def_name, reference.def_name, "def", reference.field_path])?;
+            }
+            if let Some(parent_name) = &def.parent_name {
+                insert.execute(params![def.def_name, parent_name, "parent", None::<String>])?;
+            }
+            for class in &def.code_references {
+                insert.execute(params![def.def_name, class, "code", None::<String>])?;
+            }
+            reference_count += def.references.len() + def.code_references.len() + def.parent_name.iter().count();
+        }
+    }
+    tx.commit()?;
+
+    let tx = connection.transaction()?;
+    let mut element_count = 0;
+    {
+        let mut insert = tx.prepare("INSERT INTO elements VALUES (?1, ?2, ?3, ?4)")?;
+        for def in defs {
+            let mut pending: Vec<(&DefElement, String)> = def.elements.iter().map(|e| (e, e.name.clone())).collect();
+            while let Some((element, path)) = pending.pop() {
+                insert.execute(params![def.def_name, path, element.name, element.content])?;
+                element_count += 1;
+                pending.extend(element.children.iter().map(|child| (child, format!("{}.{}", path, child.name))));
+            }
+        }
+    }
+    tx.commit()?;
+
+    println!("  ✓ {} definitions, {} references and {} elements written: {}",
+        defs.len(), reference_count, element_count, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DefParser;
+
+    #[test]
+    fn tables_hold_one_row_per_item() {
+        let dir = tempfile::tempdir().unwrap();
+        let defs_dir = dir.path().join("Data").join("Core").join("Defs");
+        fs::create_dir_all(&defs_dir).unwrap();
+        fs::write(defs_dir.join("Items.xml"), r#"<Defs>
+            <ThingDef Name="ResourceBase" Abstract="True"><stackLimit>75</stackLimit></ThingDef>
+            <ThingDef ParentName="ResourceBase">
+                <defName>Steel</defName>
+                <label>steel</label>
+                <comps><li Class="CompProperties_Forbiddable"/></comps>
+            </ThingDef>
+            <RecipeDef><defName>Make_Steel</defName><products><Steel>10</Steel></products></RecipeDef>
+        </Defs>"#).unwrap();
+        let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+        parser.quiet = true;
+        parser.scan_defs_directory().unwrap();
+        let defs = parser.parsed_defs;
+
+        let path = dir.path().join("out").join("defs.sqlite");
+        write_sqlite(&defs, &path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let count = |sql: &str| connection.query_row(sql, [], |row| row.get::<_, usize>(0)).unwrap();
+        assert_eq!(count("SELECT COUNT(*) FROM defs"), 3);
+        assert_eq!(count("SELECT COUNT(*) FROM tags"), defs.iter().map(|def| def.tags.len()).sum::<usize>());
+        assert!(count("SELECT COUNT(*) FROM tags WHERE def_name = 'Steel'") > 0);
+        assert_eq!(count("SELECT COUNT(*) FROM \"references\" WHERE kind = 'def'"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM \"references\" WHERE kind = 'parent' AND src = 'Steel' AND dst = 'ResourceBase'"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM \"references\" WHERE kind = 'code' AND dst = 'CompProperties_Forbiddable'"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM \"references\""), 3);
+        assert_eq!(count("SELECT COUNT(*) FROM \"references\" WHERE src = 'Make_Steel' AND dst = 'Steel' AND field_path = 'products.Steel'"), 1);
+    }
+}
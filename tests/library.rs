@@ -0,0 +1,54 @@
+//! The library API as a dependent crate sees it.
+
+use std::fs;
+use std::path::Path;
+
+use rimworld_def_viewer::{parse_defs, DefParser};
+
+fn install(dir: &Path) {
+    let defs_dir = dir.join("Data").join("Core").join("Defs");
+    fs::create_dir_all(&defs_dir).unwrap();
+    fs::write(defs_dir.join("Items.xml"), r#"<Defs>
+        <ThingDef Name="ResourceBase" Abstract="True">
+            <stackLimit>75</stackLimit>
+        </ThingDef>
+        <ThingDef ParentName="ResourceBase">
+            <defName>Steel</defName>
+            <label>steel</label>
+        </ThingDef>
+        <RecipeDef>
+            <defName>Make_Steel</defName>
+            <products><Steel>10</Steel></products>
+        </RecipeDef>
+    </Defs>"#).unwrap();
+}
+
+#[test]
+fn parse_defs_maps_references() {
+    let dir = tempfile::tempdir().unwrap();
+    install(dir.path());
+
+    let defs = parse_defs(dir.path()).unwrap();
+    assert_eq!(defs.len(), 3);
+
+    let steel = defs.iter().find(|def| def.def_name == "Steel").unwrap();
+    assert_eq!(steel.def_type, "ThingDef");
+    assert_eq!(steel.label.as_deref(), Some("steel"));
+    assert_eq!(steel.extension, "Core");
+    assert_eq!(steel.references_in, ["Make_Steel"]);
+    assert!(steel.resolved_elements.is_none());
+}
+
+#[test]
+fn def_parser_resolves_inheritance_on_request() {
+    let dir = tempfile::tempdir().unwrap();
+    install(dir.path());
+
+    let mut parser = DefParser::new(dir.path().to_string_lossy().to_string());
+    parser.quiet = true;
+    parser.scan_defs_directory().unwrap();
+    parser.resolve_inheritance();
+
+    let steel = parser.parsed_defs.iter().find(|def| def.def_name == "Steel").unwrap();
+    assert_eq!(steel.effective_field("stackLimit"), Some("75"));
+}